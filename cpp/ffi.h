@@ -6,8 +6,18 @@
 #include <cstdlib>
 #include "amxxmodule.h"
 
+static const uint32_t GENERATION_BITS = 12;
+
+static const uint32_t INDEX_BITS = 16;
+
+static const uintptr_t MAX_QUEUED_RECORDS = 1000;
+
+static const uint64_t UNIX_TIMESTAMP_THRESHOLD = 1000000000;
+
 extern "C" {
 
+cell grip_body_from_json(const void *amx, cell value, bool pretty);
+
 cell grip_body_from_string(const void *amx, const char *str);
 
 cell grip_cancel_request(const void *amx, cell cancellation);
@@ -22,15 +32,44 @@ cell grip_destroy_json_value(const void *amx, cell json_value);
 
 cell grip_destroy_options(const void *amx, cell options_handle);
 
+cell grip_destroy_owned_handles(const void *amx);
+
 cell grip_get_error_description(const void *amx, char *buffer, cell size);
 
+cell grip_get_frame_stat(const void *amx, cell stat);
+
+cell grip_get_response_attempts(const void *amx);
+
 cell grip_get_response_body_string(const void *amx, char *buffer, cell size);
 
+cell grip_get_response_header(const void *amx,
+                              const char *name,
+                              char *buffer,
+                              cell size,
+                              cell value_index);
+
+cell grip_get_response_header_name_at(const void *amx, cell index, char *buffer, cell size);
+
+cell grip_get_response_header_value_at(const void *amx, cell index, char *buffer, cell size);
+
+cell grip_get_response_header_values_count(const void *amx, const char *name);
+
+cell grip_get_response_headers_count(const void *amx);
+
+cell grip_get_response_held_time(const void *amx, float *ret);
+
 cell grip_get_response_state(const void *amx);
 
 cell grip_get_response_status_code(const void *amx);
 
-void grip_init(void (*error_logger)(const void*, const char*), const char *config_file_path);
+cell grip_handles_report(const void *amx, char *buffer, cell maxlen);
+
+cell grip_has_response_header(const void *amx, const char *name);
+
+void grip_init(void (*error_logger)(const void*, const char*),
+               void (*logger)(const char*),
+               const char *(*plugin_name_getter)(const void*),
+               const char *config_file_path);
 
 cell grip_is_request_active(cell request_id);
 
@@ -50,6 +89,8 @@ cell grip_json_array_clear(const void *amx, cell array);
 
 cell grip_json_array_get_bool(const void *amx, cell array, cell index);
 
+cell grip_json_array_get_child(const void *amx, cell array, cell index);
+
 cell grip_json_array_get_count(const void *amx, cell array);
 
 cell grip_json_array_get_float(const void *amx, cell array, cell index, float *ret);
@@ -78,8 +119,12 @@ cell grip_json_array_replace_string(const void *amx, cell array, cell index, con
 
 cell grip_json_array_replace_value(const void *amx, cell array, cell index, cell value);
 
+cell grip_json_diff(const void *amx, cell from, cell to);
+
 cell grip_json_equals(const void *amx, cell value1, cell value2);
 
+cell grip_json_freeze(const void *amx, cell value);
+
 cell grip_json_get_bool(const void *amx, cell value);
 
 cell grip_json_get_float(const void *amx, cell value, float *ret);
@@ -90,28 +135,40 @@ cell grip_json_get_string(const void *amx, cell value, char *buffer, cell buffer
 
 cell grip_json_get_type(const void *amx, cell value);
 
-cell grip_json_init_array();
+cell grip_json_init_array(const void *amx);
 
-cell grip_json_init_bool(bool value);
+cell grip_json_init_bool(const void *amx, bool value);
 
-cell grip_json_init_float(double value);
+cell grip_json_init_float(const void *amx, double value);
 
-cell grip_json_init_null();
+cell grip_json_init_null(const void *amx);
 
-cell grip_json_init_number(cell value);
+cell grip_json_init_number(const void *amx, cell value);
 
-cell grip_json_init_object();
+cell grip_json_init_object(const void *amx);
 
 cell grip_json_init_string(const void *amx, char *string);
 
+cell grip_json_is_frozen(const void *amx, cell value);
+
+cell grip_json_merge_patch(const void *amx, cell value, cell patch);
+
+cell grip_json_object_clear(const void *amx, cell object);
+
 cell grip_json_object_get_bool(const void *amx, cell object, const char *name, bool dot_notation);
 
+cell grip_json_object_get_child(const void *amx, cell object, const char *name, bool dot_notation);
+
+cell grip_json_object_get_count(const void *amx, cell object);
+
 cell grip_json_object_get_float(const void *amx,
                                 cell object,
                                 const char *name,
                                 bool dot_notation,
                                 float *ret);
 
+cell grip_json_object_get_name(const void *amx, cell object, cell index, char *buffer, cell maxlen);
+
 cell grip_json_object_get_number(const void *amx, cell object, const char *name, bool dot_notation);
 
 cell grip_json_object_get_string(const void *amx,
@@ -123,6 +180,48 @@ cell grip_json_object_get_string(const void *amx,
 
 cell grip_json_object_get_value(const void *amx, cell object, const char *name, bool dot_notation);
 
+cell grip_json_object_get_value_at(const void *amx, cell object, cell index);
+
+cell grip_json_object_has_value(const void *amx,
+                                cell object,
+                                const char *name,
+                                cell json_type_filter,
+                                bool dot_notation);
+
+cell grip_json_object_remove(const void *amx, cell object, const char *name, bool dot_notation);
+
+cell grip_json_object_set_bool(const void *amx,
+                               cell object,
+                               const char *name,
+                               bool boolean,
+                               bool dot_notation);
+
+cell grip_json_object_set_float(const void *amx,
+                                cell object,
+                                const char *name,
+                                float number,
+                                bool dot_notation);
+
+cell grip_json_object_set_null(const void *amx, cell object, const char *name, bool dot_notation);
+
+cell grip_json_object_set_number(const void *amx,
+                                 cell object,
+                                 const char *name,
+                                 cell number,
+                                 bool dot_notation);
+
+cell grip_json_object_set_string(const void *amx,
+                                 cell object,
+                                 const char *name,
+                                 const char *string,
+                                 bool dot_notation);
+
+cell grip_json_object_set_value(const void *amx,
+                                cell object,
+                                const char *name,
+                                cell value,
+                                bool dot_notation);
+
 cell grip_json_parse_file(const void *amx, char *file, char *error_buffer, cell error_buffer_size);
 
 cell grip_json_parse_response_body(const void *amx, char *error_buffer, cell error_buffer_size);
@@ -132,13 +231,87 @@ cell grip_json_parse_string(const void *amx,
                             char *error_buffer,
                             cell error_buffer_size);
 
+cell grip_json_patch(const void *amx, cell value, cell patch);
+
+cell grip_json_pointer_get_bool(const void *amx, cell value, const char *pointer);
+
+cell grip_json_pointer_get_float(const void *amx, cell value, const char *pointer, float *ret);
+
+cell grip_json_pointer_get_number(const void *amx, cell value, const char *pointer);
+
+cell grip_json_pointer_get_string(const void *amx,
+                                  cell value,
+                                  const char *pointer,
+                                  char *buffer,
+                                  cell maxlen);
+
+cell grip_json_pointer_get_value(const void *amx, cell value, const char *pointer);
+
+cell grip_json_pointer_set_bool(const void *amx, cell target, const char *pointer, bool boolean);
+
+cell grip_json_pointer_set_float(const void *amx, cell target, const char *pointer, float number);
+
+cell grip_json_pointer_set_null(const void *amx, cell target, const char *pointer);
+
+cell grip_json_pointer_set_number(const void *amx, cell target, const char *pointer, cell number);
+
+cell grip_json_pointer_set_string(const void *amx,
+                                  cell target,
+                                  const char *pointer,
+                                  const char *string);
+
+cell grip_json_pointer_set_value(const void *amx, cell target, const char *pointer, cell value);
+
+cell grip_json_promote(const void *amx, cell value);
+
+cell grip_json_query(const void *amx, cell value, const char *path);
+
+cell grip_json_release(const void *amx, cell value);
+
+cell grip_json_retain(const void *amx, cell value);
+
+cell grip_json_scope_begin(const void *amx);
+
+cell grip_json_scope_end(const void *amx);
+
+cell grip_json_serial_size(const void *amx, cell value, bool pretty, bool null_byte);
+
+cell grip_json_serial_to_file(const void *amx, cell value, const char *file, bool pretty);
+
+cell grip_json_serial_to_string(const void *amx,
+                                cell value,
+                                char *buffer,
+                                cell maxlen,
+                                bool pretty);
+
 cell grip_options_add_header(const void *amx,
                              cell options_handle,
                              const char *header_name,
                              const char *header_value);
 
+cell grip_options_set_priority(const void *amx, cell options_handle, cell priority);
+
+cell grip_options_set_rate_limit_group(const void *amx, cell options_handle, const char *group);
+
+cell grip_options_set_retry(const void *amx,
+                            cell options_handle,
+                            cell max_attempts,
+                            double base_delay,
+                            double max_delay,
+                            double jitter,
+                            cell conditions);
+
+/// Frees handles of every plugin, while their AMX'es are still valid. Paused plugins are
+/// unloaded as well, so their handles are freed and counted the same way.
+/// Plugin can keep handles for its whole lifetime, so left handles are reported as a possible
+/// leak only when their number grows between map changes.
+void grip_plugins_unloading();
+
 void grip_process_request();
 
+/// Re-reads config and applies new settings to the running module and queue.
+cell grip_reload_config(const void *amx, char *buffer, cell maxlen);
+
 cell grip_request(const void *amx,
                   cell forward_id,
                   const char *uri,
@@ -148,6 +321,15 @@ cell grip_request(const void *amx,
                   cell options_handle,
                   cell user_data);
 
+cell grip_request_custom_method(const void *amx,
+                                cell forward_id,
+                                const char *uri,
+                                cell body_handle,
+                                const char *method,
+                                void (*handler)(cell forward_handle, cell user_data),
+                                cell options_handle,
+                                cell user_data);
+
 } // extern "C"
 
 #endif // GRIP_FFI_H
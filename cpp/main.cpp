@@ -49,6 +49,33 @@ void log_error(const void* amx, const char* string) {
 	MF_LogError((AMX*)amx, AMX_ERR_NATIVE, "%s", string);
 }
 
+void log_message(const char* string) {
+	MF_Log("%s", string);
+}
+
+const char* plugin_name(const void* amx) {
+	int id = MF_FindScriptByAmx((const AMX*)amx);
+	return id < 0 ? nullptr : MF_GetScriptName(id);
+}
+
+void handles_report_command() {
+	static char report[65536];
+	grip_handles_report(nullptr, report, sizeof(report) - 1);
+
+	for (char* line = strtok(report, "\n"); line != nullptr; line = strtok(nullptr, "\n")) {
+		MF_PrintSrvConsole("%s\n", line);
+	}
+}
+
+void reload_config_command() {
+	static char report[65536];
+	grip_reload_config(nullptr, report, sizeof(report) - 1);
+
+	for (char* line = strtok(report, "\n"); line != nullptr; line = strtok(nullptr, "\n")) {
+		MF_PrintSrvConsole("%s\n", line);
+	}
+}
+
 void request_handler(cell forward_handle, cell user_data) {
 	MF_ExecuteForward(
 			forward_handle,
@@ -64,6 +91,12 @@ cell AMX_NATIVE_CALL grip_body_from_string_amxx(AMX *amx, cell *params) {
 	return grip_body_from_string(amx, str);
 }
 
+//native GripBody:grip_body_from_json(GripJSONValue:value, bool:pretty = false);
+cell AMX_NATIVE_CALL grip_body_from_json_amxx(AMX *amx, cell *params) {
+	enum { arg_count, arg_value, arg_pretty };
+	return grip_body_from_json(amx, params[arg_value], params[arg_pretty] != 0);
+}
+
 //native grip_destroy_body(GripBodyHandle:body);
 cell AMX_NATIVE_CALL grip_destroy_body_amxx(AMX *amx, cell *params) {
 	enum { arg_count, arg_body };
@@ -89,6 +122,23 @@ cell AMX_NATIVE_CALL grip_request_amxx(AMX *amx, cell *params) {
 	return grip_request(amx, handler_forward, uri, params[arg_body_handle], params[arg_type], request_handler, params[arg_options], params[arg_user_data]);
 }
 
+// native GripRequestCancellation:grip_request_custom_method(const uri[], GripBody:body, const method[], const handler[], GripRequestOptions:options = Empty_GripRequestOptions, const userData = 0);
+cell AMX_NATIVE_CALL grip_request_custom_method_amxx(AMX *amx, cell *params) {
+	enum { arg_count, arg_uri, arg_body_handle, arg_method, arg_handler, arg_options, arg_user_data };
+
+	const char* uri = MF_GetAmxString(amx, params[arg_uri], 2, &dummy);
+	const char* method = MF_GetAmxString(amx, params[arg_method], 3, &dummy);
+	const char* handler_name = MF_GetAmxString(amx, params[arg_handler], 1, &dummy);
+	cell handler_forward = MF_RegisterSPForwardByName(amx, handler_name, FP_CELL, FP_DONE);
+	if (handler_forward < 1)
+	{
+		MF_LogError(amx, AMX_ERR_NATIVE, "Function not found: %s", handler_name);
+		return 0;
+	}
+
+	return grip_request_custom_method(amx, handler_forward, uri, params[arg_body_handle], method, request_handler, params[arg_options], params[arg_user_data]);
+}
+
 cell AMX_NATIVE_CALL grip_cancel_request_amxx(AMX *amx, cell *params) {
 	enum { arg_count, arg_cancellation };
 	return grip_cancel_request(amx, params[arg_cancellation]);
@@ -98,6 +148,18 @@ cell AMX_NATIVE_CALL grip_get_response_state_amxx(AMX *amx, cell*) {
 	return grip_get_response_state(amx);
 }
 
+cell AMX_NATIVE_CALL grip_get_response_attempts_amxx(AMX *amx, cell*) {
+	return grip_get_response_attempts(amx);
+}
+
+cell AMX_NATIVE_CALL grip_get_response_held_time_amxx(AMX *amx, cell*) {
+	float ret;
+
+	grip_get_response_held_time(amx, &ret);
+
+	return amx_ftoc(ret);
+}
+
 cell AMX_NATIVE_CALL grip_get_response_status_code_amxx(AMX *amx, cell *) {
 	return grip_get_response_status_code(amx);
 }
@@ -129,11 +191,99 @@ cell AMX_NATIVE_CALL grip_get_response_body_string_amxx(AMX *amx, cell *params)
   return ret;
 }
 
+cell AMX_NATIVE_CALL grip_get_response_headers_count_amxx(AMX *amx, cell *) {
+	return grip_get_response_headers_count(amx);
+}
+
+cell AMX_NATIVE_CALL grip_get_response_header_name_at_amxx(AMX *amx, cell *params) {
+	enum { arg_count, arg_index, arg_buffer, arg_buffer_size};
+
+	ZERO_INIT_STACK_BUFFER(buffer, params[arg_buffer_size]);
+	cell ret = grip_get_response_header_name_at(amx, params[arg_index], &buffer[0], params[arg_buffer_size]);
+
+	MF_SetAmxStringSafe(amx, params[arg_buffer], &buffer[0], params[arg_buffer_size]);
+
+	return ret;
+}
+
+cell AMX_NATIVE_CALL grip_get_response_header_value_at_amxx(AMX *amx, cell *params) {
+	enum { arg_count, arg_index, arg_buffer, arg_buffer_size};
+
+	ZERO_INIT_STACK_BUFFER(buffer, params[arg_buffer_size]);
+	cell ret = grip_get_response_header_value_at(amx, params[arg_index], &buffer[0], params[arg_buffer_size]);
+
+	MF_SetAmxStringSafe(amx, params[arg_buffer], &buffer[0], params[arg_buffer_size]);
+
+	return ret;
+}
+
+cell AMX_NATIVE_CALL grip_get_response_header_amxx(AMX *amx, cell *params) {
+	enum { arg_count, arg_name, arg_buffer, arg_buffer_size, arg_value_index};
+
+	ZERO_INIT_STACK_BUFFER(buffer, params[arg_buffer_size]);
+	cell ret = grip_get_response_header(amx, MF_GetAmxString(amx, params[arg_name], 0, &dummy),
+			&buffer[0], params[arg_buffer_size], params[arg_value_index]);
+
+	MF_SetAmxStringSafe(amx, params[arg_buffer], &buffer[0], params[arg_buffer_size]);
+
+	return ret;
+}
+
+cell AMX_NATIVE_CALL grip_get_response_header_values_count_amxx(AMX *amx, cell *params) {
+	enum { arg_count, arg_name};
+
+	return grip_get_response_header_values_count(amx, MF_GetAmxString(amx, params[arg_name], 0, &dummy));
+}
+
+cell AMX_NATIVE_CALL grip_has_response_header_amxx(AMX *amx, cell *params) {
+	enum { arg_count, arg_name};
+
+	return grip_has_response_header(amx, MF_GetAmxString(amx, params[arg_name], 0, &dummy));
+}
+
 cell AMX_NATIVE_CALL grip_destroy_json_value_amxx(AMX *amx, cell *params) {
 	enum { arg_count, arg_json_value};
 	return grip_destroy_json_value(amx, params[arg_json_value]);
 }
 
+cell AMX_NATIVE_CALL grip_json_freeze_amxx(AMX *amx, cell *params) {
+	enum { arg_count, arg_value };
+
+	return grip_json_freeze(amx, params[arg_value]);
+}
+
+cell AMX_NATIVE_CALL grip_json_retain_amxx(AMX *amx, cell *params) {
+	enum { arg_count, arg_value };
+
+	return grip_json_retain(amx, params[arg_value]);
+}
+
+cell AMX_NATIVE_CALL grip_json_release_amxx(AMX *amx, cell *params) {
+	enum { arg_count, arg_value };
+
+	return grip_json_release(amx, params[arg_value]);
+}
+
+cell AMX_NATIVE_CALL grip_json_is_frozen_amxx(AMX *amx, cell *params) {
+	enum { arg_count, arg_value };
+
+	return grip_json_is_frozen(amx, params[arg_value]);
+}
+
+cell AMX_NATIVE_CALL grip_json_scope_begin_amxx(AMX *amx, cell *) {
+	return grip_json_scope_begin(amx);
+}
+
+cell AMX_NATIVE_CALL grip_json_scope_end_amxx(AMX *amx, cell *) {
+	return grip_json_scope_end(amx);
+}
+
+cell AMX_NATIVE_CALL grip_json_promote_amxx(AMX *amx, cell *params) {
+	enum { arg_count, arg_value };
+
+	return grip_json_promote(amx, params[arg_value]);
+}
+
 cell AMX_NATIVE_CALL grip_create_default_options_amxx(AMX *amx, cell *params) {
 	enum { arg_count, arg_timeout};
 
@@ -154,6 +304,27 @@ cell AMX_NATIVE_CALL grip_options_add_header_amxx(AMX *amx, cell *params) {
 			MF_GetAmxString(amx, params[arg_header_value], 1, &dummy));
 }
 
+cell AMX_NATIVE_CALL grip_options_set_retry_amxx(AMX *amx, cell *params) {
+	enum { arg_count, arg_options_handle, arg_max_attempts, arg_base_delay, arg_max_delay, arg_jitter, arg_conditions };
+
+	return grip_options_set_retry(amx, params[arg_options_handle], params[arg_max_attempts],
+			amx_ctof(params[arg_base_delay]), amx_ctof(params[arg_max_delay]),
+			amx_ctof(params[arg_jitter]), params[arg_conditions]);
+}
+
+cell AMX_NATIVE_CALL grip_options_set_priority_amxx(AMX *amx, cell *params) {
+	enum { arg_count, arg_options_handle, arg_priority };
+
+	return grip_options_set_priority(amx, params[arg_options_handle], params[arg_priority]);
+}
+
+cell AMX_NATIVE_CALL grip_options_set_rate_limit_group_amxx(AMX *amx, cell *params) {
+	enum { arg_count, arg_options_handle, arg_group };
+
+	return grip_options_set_rate_limit_group(amx, params[arg_options_handle],
+			MF_GetAmxString(amx, params[arg_group], 0, &dummy));
+}
+
 cell AMX_NATIVE_CALL grip_json_parse_response_body_amxx(AMX *amx, cell *params) {
 	enum { arg_count, arg_buffer, arg_buffer_size, arg_is_comment};
 
@@ -189,6 +360,31 @@ cell AMX_NATIVE_CALL grip_json_parse_file_amxx(AMX *amx, cell *params) {
 
 	return ret;
 }
+
+cell AMX_NATIVE_CALL grip_json_serial_size_amxx(AMX *amx, cell *params) {
+	enum { arg_count, arg_value, arg_pretty, arg_null_byte };
+
+	return grip_json_serial_size(amx, params[arg_value], params[arg_pretty] != 0, params[arg_null_byte] != 0);
+}
+
+cell AMX_NATIVE_CALL grip_json_serial_to_string_amxx(AMX *amx, cell *params) {
+	enum { arg_count, arg_value, arg_buffer, arg_buffer_size, arg_pretty };
+
+	ZERO_INIT_STACK_BUFFER(buffer, params[arg_buffer_size]);
+
+	cell ret = grip_json_serial_to_string(amx, params[arg_value], &buffer[0], params[arg_buffer_size], params[arg_pretty] != 0);
+
+	MF_SetAmxStringSafe(amx, params[arg_buffer], &buffer[0], params[arg_buffer_size]);
+
+	return ret;
+}
+
+cell AMX_NATIVE_CALL grip_json_serial_to_file_amxx(AMX *amx, cell *params) {
+	enum { arg_count, arg_value, arg_file, arg_pretty };
+
+	return grip_json_serial_to_file(amx, params[arg_value], MF_GetAmxString(amx, params[arg_file], 0, &dummy), params[arg_pretty] != 0);
+}
+
 cell AMX_NATIVE_CALL grip_json_equals_amxx(AMX *amx, cell *params) {
 	enum { arg_count, arg_value1, arg_value2 };
 	return grip_json_equals(amx, params[arg_value1], params[arg_value2]);
@@ -199,12 +395,32 @@ cell AMX_NATIVE_CALL grip_json_get_type_amxx(AMX *amx, cell *params) {
 	return grip_json_get_type(amx, params[arg_value]);
 }
 
-cell AMX_NATIVE_CALL grip_json_init_object_amxx(AMX *, cell *) {
-	return grip_json_init_object();
+cell AMX_NATIVE_CALL grip_json_query_amxx(AMX *amx, cell *params) {
+	enum { arg_count, arg_value, arg_path };
+	return grip_json_query(amx, params[arg_value], MF_GetAmxString(amx, params[arg_path], 0, &dummy));
+}
+
+cell AMX_NATIVE_CALL grip_json_merge_patch_amxx(AMX *amx, cell *params) {
+	enum { arg_count, arg_value, arg_patch };
+	return grip_json_merge_patch(amx, params[arg_value], params[arg_patch]);
+}
+
+cell AMX_NATIVE_CALL grip_json_patch_amxx(AMX *amx, cell *params) {
+	enum { arg_count, arg_value, arg_patch };
+	return grip_json_patch(amx, params[arg_value], params[arg_patch]);
+}
+
+cell AMX_NATIVE_CALL grip_json_diff_amxx(AMX *amx, cell *params) {
+	enum { arg_count, arg_from, arg_to };
+	return grip_json_diff(amx, params[arg_from], params[arg_to]);
+}
+
+cell AMX_NATIVE_CALL grip_json_init_object_amxx(AMX *amx, cell *) {
+	return grip_json_init_object(amx);
 }
 
-cell AMX_NATIVE_CALL grip_json_init_array_amxx(AMX *, cell *) {
-	return grip_json_init_array();
+cell AMX_NATIVE_CALL grip_json_init_array_amxx(AMX *amx, cell *) {
+	return grip_json_init_array(amx);
 }
 
 cell AMX_NATIVE_CALL grip_json_init_string_amxx(AMX *amx, cell *params) {
@@ -212,23 +428,23 @@ cell AMX_NATIVE_CALL grip_json_init_string_amxx(AMX *amx, cell *params) {
 	return grip_json_init_string(amx, MF_GetAmxString(amx, params[arg_string], 2, &dummy));
 }
 
-cell AMX_NATIVE_CALL grip_json_init_number_amxx(AMX *, cell *params) {
+cell AMX_NATIVE_CALL grip_json_init_number_amxx(AMX *amx, cell *params) {
 	enum { arg_count, arg_value };
-	return grip_json_init_number(params[arg_value]);
+	return grip_json_init_number(amx, params[arg_value]);
 }
 
-cell AMX_NATIVE_CALL grip_json_init_float_amxx(AMX *, cell *params) {
+cell AMX_NATIVE_CALL grip_json_init_float_amxx(AMX *amx, cell *params) {
 	enum { arg_count, arg_value };
-	return grip_json_init_float( (float) amx_ctof(params[arg_value]));
+	return grip_json_init_float(amx, (float) amx_ctof(params[arg_value]));
 }
 
-cell AMX_NATIVE_CALL grip_json_init_bool_amxx(AMX *, cell *params) {
+cell AMX_NATIVE_CALL grip_json_init_bool_amxx(AMX *amx, cell *params) {
 	enum { arg_count, arg_value };
-	return grip_json_init_bool(params[arg_value] != 0);
+	return grip_json_init_bool(amx, params[arg_value] != 0);
 }
 
-cell AMX_NATIVE_CALL grip_json_init_null_amxx(AMX *, cell *) {
-	return grip_json_init_null();
+cell AMX_NATIVE_CALL grip_json_init_null_amxx(AMX *amx, cell *) {
+	return grip_json_init_null(amx);
 }
 
 cell AMX_NATIVE_CALL grip_json_get_string_amxx(AMX *amx, cell *params) {
@@ -270,6 +486,12 @@ cell AMX_NATIVE_CALL grip_json_array_get_value_amxx(AMX *amx, cell *params) {
 	return grip_json_array_get_value(amx, params[arg_array], params[arg_index]);
 }
 
+cell AMX_NATIVE_CALL grip_json_array_get_child_amxx(AMX *amx, cell *params) {
+	enum { arg_count, arg_array, arg_index};
+
+	return grip_json_array_get_child(amx, params[arg_array], params[arg_index]);
+}
+
 cell AMX_NATIVE_CALL grip_json_array_get_string_amxx(AMX *amx, cell *params) {
 	enum { arg_count, arg_array, arg_index, arg_buffer, arg_buffer_size};
 
@@ -405,6 +627,14 @@ cell AMX_NATIVE_CALL grip_json_object_get_value_amxx(AMX *amx, cell *params) {
     return grip_json_object_get_value(amx, params[arg_object], name, params[arg_dot_notation] != 0);
 }
 
+cell AMX_NATIVE_CALL grip_json_object_get_child_amxx(AMX *amx, cell *params) {
+	enum { arg_count, arg_object, arg_name, arg_dotnot };
+
+	return grip_json_object_get_child(amx, params[arg_object],
+									  MF_GetAmxString(amx, params[arg_name], 0, &dummy),
+									  params[arg_dotnot] != 0);
+}
+
 cell AMX_NATIVE_CALL grip_json_object_get_string_amxx(AMX *amx, cell *params) {
     enum { arg_count, arg_object, arg_name, arg_buffer, arg_maxlen, arg_dotnot };
 
@@ -448,25 +678,273 @@ cell AMX_NATIVE_CALL grip_json_object_get_bool_amxx(AMX *amx, cell *params) {
 									   params[arg_dotnot] != 0);
 }
 
+cell AMX_NATIVE_CALL grip_json_object_get_count_amxx(AMX *amx, cell *params) {
+	enum { arg_count, arg_object };
+
+	return grip_json_object_get_count(amx, params[arg_object]);
+}
+
+cell AMX_NATIVE_CALL grip_json_object_get_name_amxx(AMX *amx, cell *params) {
+	enum { arg_count, arg_object, arg_index, arg_buffer, arg_maxlen };
+
+	ZERO_INIT_STACK_BUFFER(buffer, params[arg_maxlen]);
+
+	cell ret = grip_json_object_get_name(amx, params[arg_object], params[arg_index], &buffer[0], params[arg_maxlen]);
+
+	MF_SetAmxStringSafe(amx, params[arg_buffer], &buffer[0], params[arg_maxlen]);
+
+	return ret;
+}
+
+cell AMX_NATIVE_CALL grip_json_object_get_value_at_amxx(AMX *amx, cell *params) {
+	enum { arg_count, arg_object, arg_index };
+
+	return grip_json_object_get_value_at(amx, params[arg_object], params[arg_index]);
+}
+
+cell AMX_NATIVE_CALL grip_json_object_has_value_amxx(AMX *amx, cell *params) {
+	enum { arg_count, arg_object, arg_name, arg_type, arg_dotnot };
+
+	return grip_json_object_has_value(amx, params[arg_object],
+									  MF_GetAmxString(amx, params[arg_name], 0, &dummy),
+									  params[arg_type],
+									  params[arg_dotnot] != 0);
+}
+
+cell AMX_NATIVE_CALL grip_json_object_set_value_amxx(AMX *amx, cell *params) {
+	enum { arg_count, arg_object, arg_name, arg_value, arg_dotnot };
+
+	return grip_json_object_set_value(amx, params[arg_object],
+									  MF_GetAmxString(amx, params[arg_name], 0, &dummy),
+									  params[arg_value],
+									  params[arg_dotnot] != 0);
+}
+
+cell AMX_NATIVE_CALL grip_json_object_set_string_amxx(AMX *amx, cell *params) {
+	enum { arg_count, arg_object, arg_name, arg_string, arg_dotnot };
+
+	return grip_json_object_set_string(amx, params[arg_object],
+									   MF_GetAmxString(amx, params[arg_name], 0, &dummy),
+									   MF_GetAmxString(amx, params[arg_string], 1, &dummy),
+									   params[arg_dotnot] != 0);
+}
+
+cell AMX_NATIVE_CALL grip_json_object_set_number_amxx(AMX *amx, cell *params) {
+	enum { arg_count, arg_object, arg_name, arg_number, arg_dotnot };
+
+	return grip_json_object_set_number(amx, params[arg_object],
+									   MF_GetAmxString(amx, params[arg_name], 0, &dummy),
+									   params[arg_number],
+									   params[arg_dotnot] != 0);
+}
+
+cell AMX_NATIVE_CALL grip_json_object_set_float_amxx(AMX *amx, cell *params) {
+	enum { arg_count, arg_object, arg_name, arg_number, arg_dotnot };
+
+	return grip_json_object_set_float(amx, params[arg_object],
+									  MF_GetAmxString(amx, params[arg_name], 0, &dummy),
+									  amx_ctof(params[arg_number]),
+									  params[arg_dotnot] != 0);
+}
+
+cell AMX_NATIVE_CALL grip_json_object_set_bool_amxx(AMX *amx, cell *params) {
+	enum { arg_count, arg_object, arg_name, arg_boolean, arg_dotnot };
+
+	return grip_json_object_set_bool(amx, params[arg_object],
+									 MF_GetAmxString(amx, params[arg_name], 0, &dummy),
+									 params[arg_boolean] != 0,
+									 params[arg_dotnot] != 0);
+}
+
+cell AMX_NATIVE_CALL grip_json_object_set_null_amxx(AMX *amx, cell *params) {
+	enum { arg_count, arg_object, arg_name, arg_dotnot };
+
+	return grip_json_object_set_null(amx, params[arg_object],
+									 MF_GetAmxString(amx, params[arg_name], 0, &dummy),
+									 params[arg_dotnot] != 0);
+}
+
+cell AMX_NATIVE_CALL grip_json_object_remove_amxx(AMX *amx, cell *params) {
+	enum { arg_count, arg_object, arg_name, arg_dotnot };
+
+	return grip_json_object_remove(amx, params[arg_object],
+								   MF_GetAmxString(amx, params[arg_name], 0, &dummy),
+								   params[arg_dotnot] != 0);
+}
+
+cell AMX_NATIVE_CALL grip_json_object_clear_amxx(AMX *amx, cell *params) {
+	enum { arg_count, arg_object };
+
+	return grip_json_object_clear(amx, params[arg_object]);
+}
+
+cell AMX_NATIVE_CALL grip_json_pointer_get_value_amxx(AMX *amx, cell *params) {
+	enum { arg_count, arg_value, arg_pointer };
+
+	return grip_json_pointer_get_value(amx, params[arg_value], MF_GetAmxString(amx, params[arg_pointer], 0, &dummy));
+}
+
+cell AMX_NATIVE_CALL grip_json_pointer_get_string_amxx(AMX *amx, cell *params) {
+	enum { arg_count, arg_value, arg_pointer, arg_buffer, arg_maxlen };
+
+	ZERO_INIT_STACK_BUFFER(buffer, params[arg_maxlen]);
+
+	cell ret = grip_json_pointer_get_string(amx, params[arg_value],
+											MF_GetAmxString(amx, params[arg_pointer], 0, &dummy),
+											&buffer[0], params[arg_maxlen]);
+
+	MF_SetAmxStringSafe(amx, params[arg_buffer], &buffer[0], params[arg_maxlen]);
+
+	return ret;
+}
+
+cell AMX_NATIVE_CALL grip_json_pointer_get_number_amxx(AMX *amx, cell *params) {
+	enum { arg_count, arg_value, arg_pointer };
+
+	return grip_json_pointer_get_number(amx, params[arg_value], MF_GetAmxString(amx, params[arg_pointer], 0, &dummy));
+}
+
+cell AMX_NATIVE_CALL grip_json_pointer_get_float_amxx(AMX *amx, cell *params) {
+	enum { arg_count, arg_value, arg_pointer };
+
+	float ret;
+
+	grip_json_pointer_get_float(amx, params[arg_value], MF_GetAmxString(amx, params[arg_pointer], 0, &dummy), &ret);
+
+	return amx_ftoc(ret);
+}
+
+cell AMX_NATIVE_CALL grip_json_pointer_get_bool_amxx(AMX *amx, cell *params) {
+	enum { arg_count, arg_value, arg_pointer };
+
+	return grip_json_pointer_get_bool(amx, params[arg_value], MF_GetAmxString(amx, params[arg_pointer], 0, &dummy));
+}
+
+cell AMX_NATIVE_CALL grip_json_pointer_set_value_amxx(AMX *amx, cell *params) {
+	enum { arg_count, arg_target, arg_pointer, arg_value };
+
+	return grip_json_pointer_set_value(amx, params[arg_target],
+									   MF_GetAmxString(amx, params[arg_pointer], 0, &dummy),
+									   params[arg_value]);
+}
+
+cell AMX_NATIVE_CALL grip_json_pointer_set_string_amxx(AMX *amx, cell *params) {
+	enum { arg_count, arg_target, arg_pointer, arg_string };
+
+	return grip_json_pointer_set_string(amx, params[arg_target],
+										MF_GetAmxString(amx, params[arg_pointer], 0, &dummy),
+										MF_GetAmxString(amx, params[arg_string], 1, &dummy));
+}
+
+cell AMX_NATIVE_CALL grip_json_pointer_set_number_amxx(AMX *amx, cell *params) {
+	enum { arg_count, arg_target, arg_pointer, arg_number };
+
+	return grip_json_pointer_set_number(amx, params[arg_target],
+										MF_GetAmxString(amx, params[arg_pointer], 0, &dummy),
+										params[arg_number]);
+}
+
+cell AMX_NATIVE_CALL grip_json_pointer_set_float_amxx(AMX *amx, cell *params) {
+	enum { arg_count, arg_target, arg_pointer, arg_number };
+
+	return grip_json_pointer_set_float(amx, params[arg_target],
+									   MF_GetAmxString(amx, params[arg_pointer], 0, &dummy),
+									   amx_ctof(params[arg_number]));
+}
+
+cell AMX_NATIVE_CALL grip_json_pointer_set_bool_amxx(AMX *amx, cell *params) {
+	enum { arg_count, arg_target, arg_pointer, arg_boolean };
+
+	return grip_json_pointer_set_bool(amx, params[arg_target],
+									  MF_GetAmxString(amx, params[arg_pointer], 0, &dummy),
+									  params[arg_boolean] != 0);
+}
+
+cell AMX_NATIVE_CALL grip_json_pointer_set_null_amxx(AMX *amx, cell *params) {
+	enum { arg_count, arg_target, arg_pointer };
+
+	return grip_json_pointer_set_null(amx, params[arg_target], MF_GetAmxString(amx, params[arg_pointer], 0, &dummy));
+}
+
+cell AMX_NATIVE_CALL grip_handles_report_amxx(AMX *amx, cell *params) {
+	enum { arg_count, arg_buffer, arg_maxlen };
+
+	ZERO_INIT_STACK_BUFFER(buffer, params[arg_maxlen]);
+
+	cell ret = grip_handles_report(amx, &buffer[0], params[arg_maxlen]);
+
+	MF_SetAmxStringSafe(amx, params[arg_buffer], &buffer[0], params[arg_maxlen]);
+
+	return ret;
+}
+
+cell AMX_NATIVE_CALL grip_destroy_owned_handles_amxx(AMX *amx, cell *) {
+	return grip_destroy_owned_handles(amx);
+}
+
+cell AMX_NATIVE_CALL grip_reload_config_amxx(AMX *amx, cell *params) {
+	enum { arg_count, arg_buffer, arg_maxlen };
+
+	ZERO_INIT_STACK_BUFFER(buffer, params[arg_maxlen]);
+
+	cell ret = grip_reload_config(amx, &buffer[0], params[arg_maxlen]);
+
+	MF_SetAmxStringSafe(amx, params[arg_buffer], &buffer[0], params[arg_maxlen]);
+
+	return ret;
+}
+
+cell AMX_NATIVE_CALL grip_get_frame_stat_amxx(AMX *amx, cell *params) {
+	enum { arg_count, arg_stat };
+	return grip_get_frame_stat(amx, params[arg_stat]);
+}
+
 AMX_NATIVE_INFO grip_exports[] = {
 	{"grip_request", grip_request_amxx},
+	{"grip_request_custom_method", grip_request_custom_method_amxx},
 	{"grip_destroy_body", grip_destroy_body_amxx},
 	{"grip_body_from_string", grip_body_from_string_amxx},
+	{"grip_body_from_json", grip_body_from_json_amxx},
 	{"grip_cancel_request", grip_cancel_request_amxx},
 	{"grip_get_response_state", grip_get_response_state_amxx},
+	{"grip_get_response_attempts", grip_get_response_attempts_amxx},
+	{"grip_get_response_held_time", grip_get_response_held_time_amxx},
 	{"grip_is_request_active", grip_is_request_active_amxx},
 	{"grip_get_error_description", grip_get_error_description_amxx},
 	{"grip_get_response_body_string", grip_get_response_body_string_amxx},
 	{"grip_json_parse_response_body", grip_json_parse_response_body_amxx},
 	{"grip_destroy_json_value", grip_destroy_json_value_amxx},
+	{"grip_json_freeze", grip_json_freeze_amxx},
+	{"grip_json_retain", grip_json_retain_amxx},
+	{"grip_json_release", grip_json_release_amxx},
+	{"grip_json_is_frozen", grip_json_is_frozen_amxx},
+	{"grip_json_scope_begin", grip_json_scope_begin_amxx},
+	{"grip_json_scope_end", grip_json_scope_end_amxx},
+	{"grip_json_promote", grip_json_promote_amxx},
 	{"grip_create_default_options", grip_create_default_options_amxx},
 	{"grip_destroy_options", grip_destroy_options_amxx},
 	{"grip_options_add_header", grip_options_add_header_amxx},
+	{"grip_options_set_retry", grip_options_set_retry_amxx},
+	{"grip_options_set_priority", grip_options_set_priority_amxx},
+	{"grip_options_set_rate_limit_group", grip_options_set_rate_limit_group_amxx},
 	{"grip_get_response_status_code", grip_get_response_status_code_amxx},
+	{"grip_get_response_headers_count", grip_get_response_headers_count_amxx},
+	{"grip_get_response_header_name_at", grip_get_response_header_name_at_amxx},
+	{"grip_get_response_header_value_at", grip_get_response_header_value_at_amxx},
+	{"grip_get_response_header", grip_get_response_header_amxx},
+	{"grip_get_response_header_values_count", grip_get_response_header_values_count_amxx},
+	{"grip_has_response_header", grip_has_response_header_amxx},
 	{"grip_json_parse_string", grip_json_parse_string_amxx},
 	{"grip_json_parse_file", grip_json_parse_file_amxx},
+	{"grip_json_serial_size", grip_json_serial_size_amxx},
+	{"grip_json_serial_to_string", grip_json_serial_to_string_amxx},
+	{"grip_json_serial_to_file", grip_json_serial_to_file_amxx},
 	{"grip_json_equals", grip_json_equals_amxx},
 	{"grip_json_get_type", grip_json_get_type_amxx},
+	{"grip_json_query", grip_json_query_amxx},
+	{"grip_json_merge_patch", grip_json_merge_patch_amxx},
+	{"grip_json_patch", grip_json_patch_amxx},
+	{"grip_json_diff", grip_json_diff_amxx},
 	{"grip_json_init_object", grip_json_init_object_amxx},
 	{"grip_json_init_array", grip_json_init_array_amxx},
 	{"grip_json_init_string", grip_json_init_string_amxx},
@@ -479,6 +957,7 @@ AMX_NATIVE_INFO grip_exports[] = {
 	{"grip_json_get_float", grip_json_get_float_amxx},
 	{"grip_json_get_bool", grip_json_get_bool_amxx},
 	{"grip_json_array_get_value", grip_json_array_get_value_amxx},
+	{"grip_json_array_get_child", grip_json_array_get_child_amxx},
 	{"grip_json_array_get_string", grip_json_array_get_string_amxx},
 	{"grip_json_array_get_number", grip_json_array_get_number_amxx},
 	{"grip_json_array_get_float", grip_json_array_get_float_amxx},
@@ -499,10 +978,38 @@ AMX_NATIVE_INFO grip_exports[] = {
 	{"grip_json_array_remove", grip_json_array_remove_amxx},
 	{"grip_json_array_clear", grip_json_array_clear_amxx},
     {"grip_json_object_get_value", grip_json_object_get_value_amxx},
+    {"grip_json_object_get_child", grip_json_object_get_child_amxx},
     {"grip_json_object_get_string", grip_json_object_get_string_amxx},
 	{"grip_json_object_get_number", grip_json_object_get_number_amxx},
 	{"grip_json_object_get_float", grip_json_object_get_float_amxx},
 	{"grip_json_object_get_bool", grip_json_object_get_bool_amxx},
+	{"grip_json_object_get_count", grip_json_object_get_count_amxx},
+	{"grip_json_object_get_name", grip_json_object_get_name_amxx},
+	{"grip_json_object_get_value_at", grip_json_object_get_value_at_amxx},
+	{"grip_json_object_has_value", grip_json_object_has_value_amxx},
+	{"grip_json_object_set_value", grip_json_object_set_value_amxx},
+	{"grip_json_object_set_string", grip_json_object_set_string_amxx},
+	{"grip_json_object_set_number", grip_json_object_set_number_amxx},
+	{"grip_json_object_set_float", grip_json_object_set_float_amxx},
+	{"grip_json_object_set_bool", grip_json_object_set_bool_amxx},
+	{"grip_json_object_set_null", grip_json_object_set_null_amxx},
+	{"grip_json_object_remove", grip_json_object_remove_amxx},
+	{"grip_json_object_clear", grip_json_object_clear_amxx},
+	{"grip_json_pointer_get_value", grip_json_pointer_get_value_amxx},
+	{"grip_json_pointer_get_string", grip_json_pointer_get_string_amxx},
+	{"grip_json_pointer_get_number", grip_json_pointer_get_number_amxx},
+	{"grip_json_pointer_get_float", grip_json_pointer_get_float_amxx},
+	{"grip_json_pointer_get_bool", grip_json_pointer_get_bool_amxx},
+	{"grip_json_pointer_set_value", grip_json_pointer_set_value_amxx},
+	{"grip_json_pointer_set_string", grip_json_pointer_set_string_amxx},
+	{"grip_json_pointer_set_number", grip_json_pointer_set_number_amxx},
+	{"grip_json_pointer_set_float", grip_json_pointer_set_float_amxx},
+	{"grip_json_pointer_set_bool", grip_json_pointer_set_bool_amxx},
+	{"grip_json_pointer_set_null", grip_json_pointer_set_null_amxx},
+	{"grip_handles_report", grip_handles_report_amxx},
+	{"grip_destroy_owned_handles", grip_destroy_owned_handles_amxx},
+	{"grip_reload_config", grip_reload_config_amxx},
+	{"grip_get_frame_stat", grip_get_frame_stat_amxx},
 	{nullptr, nullptr}
 };
 
@@ -510,12 +1017,18 @@ AMX_NATIVE_INFO grip_exports[] = {
 void OnAmxxAttach()
 {
 	MF_AddNatives(grip_exports);
+	REG_SVR_COMMAND("grip_handles", handles_report_command);
+	REG_SVR_COMMAND("grip_reload_config", reload_config_command);
 }
 
 void OnPluginsLoaded() {
     char configFilePath[MAX_PATH];
     MF_BuildPathnameR(configFilePath, sizeof(configFilePath), "%s/grip.ini", MF_GetLocalInfo("amxx_configsdir", "addons/amxmodx/configs"));
-    grip_init(log_error, configFilePath);
+    grip_init(log_error, log_message, plugin_name, configFilePath);
+}
+
+void OnPluginsUnloading() {
+	grip_plugins_unloading();
 }
 
 void OnPluginsUnloaded() {
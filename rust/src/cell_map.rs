@@ -29,55 +29,325 @@
  *
  */
 
-use std::collections::HashMap;
+use crate::errors::*;
+use std::collections::VecDeque;
+use std::os::raw::c_void;
+use std::time::Instant;
 
-/// Just a simple, general and limited abstraction for storing Pawn handle id's
-pub struct CellMap<T> {
-    inner: HashMap<isize, T>,
-    counter: isize,
+/// Layout of the handle: 3 bits of kind, 12 bits of generation and 16 bits of slot index.
+/// Kind is never zero, so valid handles are always positive and -1 stays "empty" handle.
+/// Freed slots are reused in FIFO order, so generation of the slot wraps around
+/// only after 4096 reuses of every free slot.
+const INDEX_BITS: u32 = 16;
+const GENERATION_BITS: u32 = 12;
+const KIND_SHIFT: u32 = INDEX_BITS + GENERATION_BITS;
+const INDEX_MASK: isize = (1 << INDEX_BITS) - 1;
+const GENERATION_MASK: isize = (1 << GENERATION_BITS) - 1;
+const MAX_HANDLES: usize = 1 << INDEX_BITS;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum HandleKind {
+    Body = 1,
+    Cancellation = 2,
+    JSONValue = 3,
+    Options = 4,
 }
 
-impl<T> Default for CellMap<T> {
-    fn default() -> Self {
-        CellMap::new()
+impl HandleKind {
+    fn from_tag(tag: isize) -> Option<HandleKind> {
+        match tag {
+            1 => Some(HandleKind::Body),
+            2 => Some(HandleKind::Cancellation),
+            3 => Some(HandleKind::JSONValue),
+            4 => Some(HandleKind::Options),
+            _ => None,
+        }
+    }
+
+    /// Name of the Pawn tag.
+    pub fn name(self) -> &'static str {
+        match self {
+            HandleKind::Body => "GripBody",
+            HandleKind::Cancellation => "GripRequestCancellation",
+            HandleKind::JSONValue => "GripJSONValue",
+            HandleKind::Options => "GripRequestOptions",
+        }
     }
 }
+
+/// Plugin (AMX) which created the handle and the time of creation.
+pub struct HandleInfo {
+    pub owner: *const c_void,
+    pub created: Instant,
+}
+
+struct Slot<T> {
+    generation: isize,
+    entry: Option<(T, HandleInfo)>,
+}
+
+/// Just a simple, general and limited abstraction for storing Pawn handle id's.
+/// Handles are tagged with the kind and generation of the slot, so handles of another
+/// kind and handles which were destroyed are detected.
+pub struct CellMap<T> {
+    kind: HandleKind,
+    slots: Vec<Slot<T>>,
+    free_slots: VecDeque<usize>,
+}
+
 impl<T> CellMap<T> {
-    pub fn new() -> CellMap<T> {
+    pub fn new(kind: HandleKind) -> CellMap<T> {
         CellMap {
-            inner: HashMap::new(),
-            counter: 1,
+            kind,
+            slots: vec![],
+            free_slots: VecDeque::new(),
         }
     }
 
-    /// Returns ID which will be used next time.
-    pub fn peek_id(&self) -> isize {
-        self.counter
+    pub fn kind(&self) -> HandleKind {
+        self.kind
+    }
+
+    fn encode(&self, index: usize, generation: isize) -> isize {
+        ((self.kind as isize) << KIND_SHIFT) | (generation << INDEX_BITS) | index as isize
+    }
+
+    /// Returns slot index of the handle, or describes why the handle is invalid.
+    fn decode(&self, id: isize) -> Result<usize> {
+        if id <= 0 {
+            bail!(
+                "handle {} is empty/invalid, expected {}",
+                id,
+                self.kind.name()
+            );
+        }
+
+        match HandleKind::from_tag(id >> KIND_SHIFT) {
+            Some(kind) if kind == self.kind => (),
+            Some(kind) => bail!(
+                "handle {} is a {}, expected {}",
+                id,
+                kind.name(),
+                self.kind.name()
+            ),
+            None => bail!("handle {} is not a valid {}", id, self.kind.name()),
+        }
+
+        let index = (id & INDEX_MASK) as usize;
+        match self.slots.get(index) {
+            Some(slot)
+                if slot.generation == (id >> INDEX_BITS) & GENERATION_MASK
+                    && slot.entry.is_some() =>
+            {
+                Ok(index)
+            }
+            Some(_) => bail!("{} handle {} was destroyed", self.kind.name(), id),
+            None => bail!("{} handle {} doesn't exist", self.kind.name(), id),
+        }
+    }
+
+    /// Returns ID which will be used next time, or error if the handle limit is reached.
+    pub fn peek_id(&self) -> Result<isize> {
+        match self.free_slots.front() {
+            Some(&index) => Ok(self.encode(index, self.slots[index].generation)),
+            None if self.slots.len() < MAX_HANDLES => Ok(self.encode(self.slots.len(), 0)),
+            None => bail!(ffi_error(format!(
+                "Can't create {} handle, limit of {} live handles is reached",
+                self.kind.name(),
+                MAX_HANDLES
+            ))),
+        }
+    }
+
+    /// Inserts desired item owned by `owner` and returns generated id which is always greater than 1
+    pub fn insert_with_unique_id(&mut self, owner: *const c_void, item: T) -> Result<isize> {
+        let id = self.peek_id()?;
+        let entry = Some((
+            item,
+            HandleInfo {
+                owner,
+                created: Instant::now(),
+            },
+        ));
+
+        match self.free_slots.pop_front() {
+            Some(index) => self.slots[index].entry = entry,
+            None => self.slots.push(Slot {
+                generation: 0,
+                entry,
+            }),
+        }
+
+        Ok(id)
     }
 
-    /// Inserts desired item and returns generated id which is always greater than 1
-    pub fn insert_with_unique_id(&mut self, item: T) -> isize {
-        assert!(self.counter >= 1);
+    fn remove_slot(&mut self, index: usize) -> Option<T> {
+        let slot = &mut self.slots[index];
+        let (item, _) = slot.entry.take()?;
+        slot.generation = (slot.generation + 1) & GENERATION_MASK;
+        self.free_slots.push_back(index);
 
-        assert!(self.inner.insert(self.counter as isize, item).is_none());
-        self.counter += 1;
+        Some(item)
+    }
+
+    pub fn remove_with_id(&mut self, id: isize) -> Result<T> {
+        let index = self.decode(id)?;
+        Ok(self.remove_slot(index).unwrap())
+    }
 
-        self.counter - 1
+    pub fn get_with_id(&self, id: isize) -> Result<&T> {
+        let index = self.decode(id)?;
+        Ok(&self.slots[index].entry.as_ref().unwrap().0)
     }
 
-    pub fn remove_with_id(&mut self, id: isize) -> Option<T> {
-        self.inner.remove(&id)
+    pub fn get_mut_with_id(&mut self, id: isize) -> Result<&mut T> {
+        let index = self.decode(id)?;
+        Ok(&mut self.slots[index].entry.as_mut().unwrap().0)
     }
 
-    pub fn get_with_id(&self, id: isize) -> Option<&T> {
-        self.inner.get(&id)
+    /// Removes every item created by `owner` and returns their count.
+    pub fn remove_owned_by(&mut self, owner: *const c_void) -> usize {
+        let indexes: Vec<_> = self
+            .slots
+            .iter()
+            .enumerate()
+            .filter(|(_, slot)| match &slot.entry {
+                Some((_, info)) => info.owner == owner,
+                None => false,
+            })
+            .map(|(index, _)| index)
+            .collect();
+
+        for index in &indexes {
+            self.remove_slot(*index);
+        }
+
+        indexes.len()
     }
 
-    pub fn get_mut_with_id(&mut self, id: isize) -> Option<&mut T> {
-        self.inner.get_mut(&id)
+    pub fn infos(&self) -> impl Iterator<Item = (isize, &HandleInfo)> {
+        self.slots
+            .iter()
+            .enumerate()
+            .filter_map(move |(index, slot)| {
+                slot.entry
+                    .as_ref()
+                    .map(|(_, info)| (self.encode(index, slot.generation), info))
+            })
     }
 
     pub fn clear(&mut self) {
-        self.inner.clear();
+        for index in 0..self.slots.len() {
+            self.remove_slot(index);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ptr::null;
+
+    #[test]
+    fn handles_are_tagged() {
+        let mut bodies = CellMap::new(HandleKind::Body);
+        let mut options = CellMap::new(HandleKind::Options);
+
+        let body = bodies.insert_with_unique_id(null(), 1).unwrap();
+        let option = options.insert_with_unique_id(null(), 2).unwrap();
+        assert!(body > 0 && option > 0 && body != option);
+
+        assert_eq!(*bodies.get_with_id(body).unwrap(), 1);
+        assert_eq!(
+            format!("{}", bodies.get_with_id(option).unwrap_err()),
+            format!(
+                "handle {} is a GripRequestOptions, expected GripBody",
+                option
+            )
+        );
+        assert!(bodies.get_with_id(-1).is_err());
+        assert!(bodies.get_with_id(0).is_err());
+    }
+
+    #[test]
+    fn destroyed_handles_are_detected() {
+        let mut map = CellMap::new(HandleKind::JSONValue);
+        let first = map.insert_with_unique_id(null(), 1).unwrap();
+        assert_eq!(map.remove_with_id(first).unwrap(), 1);
+
+        assert_eq!(
+            map.peek_id().unwrap(),
+            map.insert_with_unique_id(null(), 2).unwrap()
+        );
+        assert_eq!(
+            format!("{}", map.get_with_id(first).unwrap_err()),
+            format!("GripJSONValue handle {} was destroyed", first)
+        );
+        assert!(map.remove_with_id(first).is_err());
+
+        let plugin = 0u8;
+        let owner = &plugin as *const u8 as *const c_void;
+        let owned = map.insert_with_unique_id(owner, 3).unwrap();
+        assert_eq!(map.remove_owned_by(owner), 1);
+        assert!(map.get_with_id(owned).is_err());
+        assert_eq!(map.infos().count(), 1);
+
+        map.clear();
+        assert_eq!(map.infos().count(), 0);
+    }
+
+    #[test]
+    fn stale_handles_are_rejected_after_many_reuses() {
+        let mut map = CellMap::new(HandleKind::JSONValue);
+        let stale = map.insert_with_unique_id(null(), 0).unwrap();
+        map.remove_with_id(stale).unwrap();
+
+        // The only free slot is reused every time.
+        for i in 1..=300 {
+            let id = map.insert_with_unique_id(null(), i).unwrap();
+            assert!(map.get_with_id(stale).is_err());
+            map.remove_with_id(id).unwrap();
+        }
+
+        map.insert_with_unique_id(null(), 301).unwrap();
+        assert!(map.get_with_id(stale).is_err());
+    }
+
+    #[test]
+    fn handle_limit_is_reported() {
+        let mut map = CellMap::new(HandleKind::Options);
+        let ids: Vec<_> = (0..MAX_HANDLES)
+            .map(|i| map.insert_with_unique_id(null(), i).unwrap())
+            .collect();
+
+        assert!(ids.iter().all(|id| *id > 0));
+        assert!(map.peek_id().is_err());
+        assert_eq!(
+            format!("{}", map.insert_with_unique_id(null(), 0).unwrap_err()),
+            "FFI Error: Can't create GripRequestOptions handle, limit of 65536 live handles is reached"
+        );
+
+        map.remove_with_id(ids[0]).unwrap();
+        assert!(map.insert_with_unique_id(null(), 0).is_ok());
+    }
+
+    #[test]
+    fn free_slots_are_reused_in_fifo_order() {
+        let mut map = CellMap::new(HandleKind::Body);
+        let first = map.insert_with_unique_id(null(), 1).unwrap();
+        let second = map.insert_with_unique_id(null(), 2).unwrap();
+
+        map.remove_with_id(first).unwrap();
+        map.remove_with_id(second).unwrap();
+
+        let index = |id: isize| id & INDEX_MASK;
+        assert_eq!(
+            index(map.insert_with_unique_id(null(), 3).unwrap()),
+            index(first)
+        );
+        assert_eq!(
+            index(map.insert_with_unique_id(null(), 4).unwrap()),
+            index(second)
+        );
     }
 }
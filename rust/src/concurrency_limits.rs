@@ -0,0 +1,225 @@
+/*
+ * gRIP
+ * Copyright (c) 2018 Alik Aslanyan <cplusplus256@gmail.com>
+ *
+ *
+ *    This program is free software; you can redistribute it and/or modify it
+ *    under the terms of the GNU General Public License as published by the
+ *    Free Software Foundation; either version 3 of the License, or (at
+ *    your option) any later version.
+ *
+ *    This program is distributed in the hope that it will be useful, but
+ *    WITHOUT ANY WARRANTY; without even the implied warranty of
+ *    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+ *    General Public License for more details.
+ *
+ *    You should have received a copy of the GNU General Public License
+ *    along with this program; if not, write to the Free Software Foundation,
+ *    Inc., 59 Temple Place, Suite 330, Boston, MA  02111-1307  USA
+ *
+ *    In addition, as a special exception, the author gives permission to
+ *    link the code of this program with the Half-Life Game Engine ("HL
+ *    Engine") and Modified Game Libraries ("MODs") developed by Valve,
+ *    L.L.C ("Valve").  You must obey the GNU General Public License in all
+ *    respects for all of the code used other than the HL Engine and MODs
+ *    from Valve.  If you modify this file, you may extend this exception
+ *    to your version of the file, but you are not obligated to do so.  If
+ *    you do not wish to do so, delete this exception statement from your
+ *    version.
+ *
+ */
+
+use crate::networking_queue::RequestPriority;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+/// Limits number of the requests in flight, overall and per host.
+/// Requests above the limits wait in FIFO order of their priority, higher priorities go first.
+/// Request to the busy host doesn't delay waiting requests to the other hosts.
+#[derive(Debug)]
+pub struct ConcurrencyLimits<T> {
+    /// Maximum number of the requests in flight, zero means unlimited.
+    pub max_in_flight: usize,
+    /// Maximum number of the requests in flight to the single host, zero means unlimited.
+    pub max_in_flight_per_host: usize,
+    in_flight: usize,
+    in_flight_per_host: HashMap<String, usize>,
+    /// Id, host and job of the waiting requests.
+    waiting: BTreeMap<RequestPriority, VecDeque<(usize, String, T)>>,
+    next_id: usize,
+}
+
+impl<T> Default for ConcurrencyLimits<T> {
+    fn default() -> Self {
+        ConcurrencyLimits {
+            max_in_flight: 0,
+            max_in_flight_per_host: 0,
+            in_flight: 0,
+            in_flight_per_host: HashMap::new(),
+            waiting: BTreeMap::new(),
+            next_id: 0,
+        }
+    }
+}
+
+impl<T> ConcurrencyLimits<T> {
+    /// Adds waiting job and returns its id, which can be used to remove the job before it's ready.
+    pub fn push(&mut self, host: String, priority: RequestPriority, job: T) -> usize {
+        let id = self.next_id;
+        self.next_id = self.next_id.wrapping_add(1);
+        self.waiting
+            .entry(priority)
+            .or_default()
+            .push_back((id, host, job));
+
+        id
+    }
+
+    /// Removes waiting job, so it stops waiting for the limits. Returns `None` if job isn't waiting.
+    pub fn remove(&mut self, id: usize) -> Option<T> {
+        for waiting in self.waiting.values_mut() {
+            if let Some(index) = waiting.iter().position(|(job_id, _, _)| *job_id == id) {
+                return waiting.remove(index).map(|(_, _, job)| job);
+            }
+        }
+
+        None
+    }
+
+    /// Takes waiting jobs, which fit into the limits, and counts them as in flight.
+    pub fn pop_ready(&mut self) -> Vec<(String, T)> {
+        let mut ready = Vec::new();
+        for waiting in self.waiting.values_mut().rev() {
+            let mut index = 0;
+            while index < waiting.len() && !is_full(self.in_flight, self.max_in_flight) {
+                let host_in_flight = self
+                    .in_flight_per_host
+                    .get(&waiting[index].1)
+                    .cloned()
+                    .unwrap_or(0);
+
+                if is_full(host_in_flight, self.max_in_flight_per_host) {
+                    index += 1;
+                    continue;
+                }
+
+                let (_, host, job) = waiting.remove(index).unwrap();
+                self.in_flight += 1;
+                *self.in_flight_per_host.entry(host.clone()).or_insert(0) += 1;
+                ready.push((host, job));
+            }
+        }
+
+        ready
+    }
+
+    /// Must be called for every job returned by `pop_ready`, when it's done.
+    pub fn finish(&mut self, host: &str) {
+        self.in_flight -= 1;
+        let host_in_flight = self.in_flight_per_host.get_mut(host).unwrap();
+        *host_in_flight -= 1;
+        if *host_in_flight == 0 {
+            self.in_flight_per_host.remove(host);
+        }
+    }
+
+    pub fn in_flight(&self) -> usize {
+        self.in_flight
+    }
+
+    pub fn waiting(&self) -> usize {
+        self.waiting.values().map(VecDeque::len).sum()
+    }
+}
+
+fn is_full(count: usize, limit: usize) -> bool {
+    limit != 0 && count >= limit
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn jobs(ready: Vec<(String, usize)>) -> Vec<usize> {
+        ready.into_iter().map(|(_, job)| job).collect()
+    }
+
+    #[test]
+    fn limits() {
+        let mut limits = ConcurrencyLimits {
+            max_in_flight: 3,
+            max_in_flight_per_host: 2,
+            ..Default::default()
+        };
+
+        for (job, host) in ["a", "a", "a", "b", "b"].iter().enumerate() {
+            limits.push(host.to_string(), RequestPriority::Normal, job);
+        }
+
+        assert_eq!(jobs(limits.pop_ready()), vec![0, 1, 3]);
+        assert_eq!((limits.in_flight(), limits.waiting()), (3, 2));
+        assert!(limits.pop_ready().is_empty());
+
+        limits.finish("a");
+        assert_eq!(jobs(limits.pop_ready()), vec![2]);
+
+        limits.finish("b");
+        assert_eq!(jobs(limits.pop_ready()), vec![4]);
+        assert_eq!((limits.in_flight(), limits.waiting()), (3, 0));
+
+        for host in &["a", "a", "b"] {
+            limits.finish(host);
+        }
+        assert_eq!(limits.in_flight(), 0);
+    }
+
+    #[test]
+    fn removing() {
+        let mut limits = ConcurrencyLimits {
+            max_in_flight: 1,
+            ..Default::default()
+        };
+
+        let first = limits.push("a".to_owned(), RequestPriority::Normal, 0);
+        let second = limits.push("a".to_owned(), RequestPriority::Normal, 1);
+        limits.push("a".to_owned(), RequestPriority::Normal, 2);
+        assert_eq!(jobs(limits.pop_ready()), vec![0]);
+
+        assert_eq!(limits.remove(first), None);
+        assert_eq!(limits.remove(second), Some(1));
+        assert_eq!(limits.waiting(), 1);
+
+        limits.finish("a");
+        assert_eq!(jobs(limits.pop_ready()), vec![2]);
+    }
+
+    #[test]
+    fn priorities() {
+        let mut limits = ConcurrencyLimits {
+            max_in_flight: 1,
+            ..Default::default()
+        };
+
+        limits.push("a".to_owned(), RequestPriority::Low, 0);
+        limits.push("a".to_owned(), RequestPriority::Normal, 1);
+        limits.push("a".to_owned(), RequestPriority::High, 2);
+        limits.push("a".to_owned(), RequestPriority::High, 3);
+
+        let mut order = vec![];
+        while limits.waiting() != 0 {
+            order.extend(jobs(limits.pop_ready()));
+            limits.finish("a");
+        }
+        assert_eq!(order, vec![2, 3, 1, 0]);
+    }
+
+    #[test]
+    fn unlimited() {
+        let mut limits = ConcurrencyLimits::default();
+        for job in 0..100 {
+            limits.push("a".to_owned(), RequestPriority::Normal, job);
+        }
+
+        assert_eq!(limits.pop_ready().len(), 100);
+        assert_eq!((limits.in_flight(), limits.waiting()), (100, 0));
+    }
+}
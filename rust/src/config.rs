@@ -0,0 +1,581 @@
+/*
+ * gRIP
+ * Copyright (c) 2018 Alik Aslanyan <cplusplus256@gmail.com>
+ *
+ *
+ *    This program is free software; you can redistribute it and/or modify it
+ *    under the terms of the GNU General Public License as published by the
+ *    Free Software Foundation; either version 3 of the License, or (at
+ *    your option) any later version.
+ *
+ *    This program is distributed in the hope that it will be useful, but
+ *    WITHOUT ANY WARRANTY; without even the implied warranty of
+ *    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+ *    General Public License for more details.
+ *
+ *    You should have received a copy of the GNU General Public License
+ *    along with this program; if not, write to the Free Software Foundation,
+ *    Inc., 59 Temple Place, Suite 330, Boston, MA  02111-1307  USA
+ *
+ *    In addition, as a special exception, the author gives permission to
+ *    link the code of this program with the Half-Life Game Engine ("HL
+ *    Engine") and Modified Game Libraries ("MODs") developed by Valve,
+ *    L.L.C ("Valve").  You must obey the GNU General Public License in all
+ *    respects for all of the code used other than the HL Engine and MODs
+ *    from Valve.  If you modify this file, you may extend this exception
+ *    to your version of the file, but you are not obligated to do so.  If
+ *    you do not wish to do so, delete this exception statement from your
+ *    version.
+ *
+ */
+
+use crate::rate_limits::RateLimitGroup;
+use ini::Ini;
+use log::LevelFilter;
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+use std::path::Path;
+use std::str::FromStr;
+
+/// Typed grip.ini. Every setting has default, so module is able to start without config.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    /// `queue.callbacks-per-frame`, maximum number of executed callbacks per frame.
+    pub callbacks_per_frame: usize,
+    /// `queue.microseconds-delay-between-attempts`, in what periods callbacks should be called.
+    pub microseconds_delay_between_attempts: usize,
+    /// `queue.frame-budget-microseconds`, time callbacks can consume per frame, zero means unlimited.
+    pub frame_budget_microseconds: usize,
+    /// `network.max-rate-limit-hold-seconds`, how long requests can be held, when host asks to slow down.
+    pub max_rate_limit_hold_seconds: usize,
+    /// `network.max-concurrent-requests`, zero means unlimited.
+    pub max_concurrent_requests: usize,
+    /// `network.max-concurrent-requests-per-host`, zero means unlimited.
+    pub max_concurrent_requests_per_host: usize,
+    /// `log.level`, minimal level of the messages, which are written to the AMXX log.
+    pub log_level: LevelFilter,
+    /// `[plugin-weights]`, how many callbacks of the plugin are executed in its turn.
+    /// Plugins without weight have weight 1, so they're dispatched round-robin.
+    pub plugin_weights: HashMap<String, usize>,
+    /// `[rate-limit-groups]`, token buckets which requests can be assigned to.
+    pub rate_limit_groups: HashMap<String, RateLimitGroup>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            callbacks_per_frame: 1,
+            microseconds_delay_between_attempts: 33000,
+            frame_budget_microseconds: 0,
+            max_rate_limit_hold_seconds: 60,
+            max_concurrent_requests: 0,
+            max_concurrent_requests_per_host: 0,
+            log_level: LevelFilter::Warn,
+            plugin_weights: HashMap::new(),
+            rate_limit_groups: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+/// Problem found while loading config. Line is 1-based.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigMessage {
+    pub severity: Severity,
+    pub line: Option<usize>,
+    pub text: String,
+}
+
+impl Display for ConfigMessage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => "Warning",
+            Severity::Error => "Error",
+        };
+
+        match self.line {
+            Some(line) => write!(f, "{} at line {}: {}", severity, line, self.text),
+            None => write!(f, "{}: {}", severity, self.text),
+        }
+    }
+}
+
+/// Setting which differs between running and reloaded config.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigChange {
+    pub key: String,
+    pub old: String,
+    pub new: String,
+}
+
+impl Display for ConfigChange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {} -> {}", self.key, self.old, self.new)
+    }
+}
+
+/// Collects messages and finds lines of the keys, since ini parser doesn't keep them.
+struct Loader<'a> {
+    source: &'a str,
+    messages: Vec<ConfigMessage>,
+}
+
+impl<'a> Loader<'a> {
+    fn push(&mut self, severity: Severity, section: Option<&str>, key: Option<&str>, text: String) {
+        let line = self.line_of(section, key);
+        self.messages.push(ConfigMessage {
+            severity,
+            line,
+            text,
+        });
+    }
+
+    /// Line of the last definition of the key (or section header if key is `None`),
+    /// because the last definition is the one which takes effect.
+    fn line_of(&self, section: Option<&str>, key: Option<&str>) -> Option<usize> {
+        let mut current_section = None;
+        let mut found = None;
+
+        for (index, line) in self.source.lines().enumerate() {
+            let line = line.trim();
+            if line.starts_with('[') && line.ends_with(']') {
+                current_section = Some(line[1..line.len() - 1].trim());
+                if key.is_none() && current_section == section {
+                    found = Some(index + 1);
+                }
+            } else if let (Some(key), Some(separator)) = (key, line.find(&['=', ':'][..])) {
+                if current_section == section && line[..separator].trim() == key {
+                    found = Some(index + 1);
+                }
+            }
+        }
+
+        found
+    }
+
+    fn parse<T: FromStr>(&mut self, section: &str, key: &str, value: &str) -> Option<T>
+    where
+        T::Err: Display,
+    {
+        match value.parse() {
+            Ok(value) => Some(value),
+            Err(e) => {
+                self.push(
+                    Severity::Error,
+                    Some(section),
+                    Some(key),
+                    format!(
+                        "Invalid value `{}` of the \"{}.{}\": {}. Using default",
+                        value, section, key, e
+                    ),
+                );
+                None
+            }
+        }
+    }
+}
+
+impl Config {
+    pub fn plugin_weight(&self, plugin: &str) -> usize {
+        self.plugin_weights.get(plugin).cloned().unwrap_or(1)
+    }
+
+    /// Loads config from file. Missing file isn't an error, defaults are used instead.
+    pub fn load_from_file<P: AsRef<Path>>(path: P) -> (Config, Vec<ConfigMessage>) {
+        let path = path.as_ref();
+        match std::fs::read_to_string(path) {
+            Ok(source) => Config::load_from_str(&source),
+            Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => (
+                Config::default(),
+                vec![ConfigMessage {
+                    severity: Severity::Warning,
+                    line: None,
+                    text: format!("Config {} doesn't exist. Using defaults", path.display()),
+                }],
+            ),
+            Err(e) => (
+                Config::default(),
+                vec![ConfigMessage {
+                    severity: Severity::Error,
+                    line: None,
+                    text: format!(
+                        "Can't read config {}: {}. Using defaults",
+                        path.display(),
+                        e
+                    ),
+                }],
+            ),
+        }
+    }
+
+    /// Applies settings of the reloaded config, every setting can be changed at runtime.
+    /// Returns every changed setting.
+    pub fn reload(&mut self, new: &Config) -> Vec<ConfigChange> {
+        let mut changes = vec![];
+
+        fn change<T: PartialEq + Display + Clone>(
+            changes: &mut Vec<ConfigChange>,
+            key: &str,
+            current: &mut T,
+            new: &T,
+        ) {
+            if current != new {
+                changes.push(ConfigChange {
+                    key: key.to_owned(),
+                    old: current.to_string(),
+                    new: new.to_string(),
+                });
+
+                *current = new.clone();
+            }
+        }
+
+        change(
+            &mut changes,
+            "queue.callbacks-per-frame",
+            &mut self.callbacks_per_frame,
+            &new.callbacks_per_frame,
+        );
+        change(
+            &mut changes,
+            "queue.microseconds-delay-between-attempts",
+            &mut self.microseconds_delay_between_attempts,
+            &new.microseconds_delay_between_attempts,
+        );
+        change(
+            &mut changes,
+            "queue.frame-budget-microseconds",
+            &mut self.frame_budget_microseconds,
+            &new.frame_budget_microseconds,
+        );
+        change(
+            &mut changes,
+            "network.max-rate-limit-hold-seconds",
+            &mut self.max_rate_limit_hold_seconds,
+            &new.max_rate_limit_hold_seconds,
+        );
+        change(
+            &mut changes,
+            "network.max-concurrent-requests",
+            &mut self.max_concurrent_requests,
+            &new.max_concurrent_requests,
+        );
+        change(
+            &mut changes,
+            "network.max-concurrent-requests-per-host",
+            &mut self.max_concurrent_requests_per_host,
+            &new.max_concurrent_requests_per_host,
+        );
+
+        change(
+            &mut changes,
+            "log.level",
+            &mut self.log_level,
+            &new.log_level,
+        );
+
+        let mut plugins: Vec<_> = self
+            .plugin_weights
+            .keys()
+            .chain(new.plugin_weights.keys())
+            .cloned()
+            .collect();
+        plugins.sort();
+        plugins.dedup();
+        for plugin in plugins {
+            change(
+                &mut changes,
+                &format!("plugin-weights.{}", plugin),
+                &mut self.plugin_weight(&plugin),
+                &new.plugin_weight(&plugin),
+            );
+        }
+        self.plugin_weights = new.plugin_weights.clone();
+
+        let mut groups: Vec<_> = self
+            .rate_limit_groups
+            .keys()
+            .chain(new.rate_limit_groups.keys())
+            .cloned()
+            .collect();
+        groups.sort();
+        groups.dedup();
+        for group in groups {
+            let describe = |config: &Config| {
+                config
+                    .rate_limit_groups
+                    .get(&group)
+                    .map_or_else(|| "none".to_owned(), RateLimitGroup::to_string)
+            };
+            change(
+                &mut changes,
+                &format!("rate-limit-groups.{}", group),
+                &mut describe(self),
+                &describe(new),
+            );
+        }
+        self.rate_limit_groups = new.rate_limit_groups.clone();
+
+        changes
+    }
+
+    /// Loads config from string. Invalid values are reported and replaced with defaults.
+    pub fn load_from_str(source: &str) -> (Config, Vec<ConfigMessage>) {
+        let mut config = Config::default();
+        let mut loader = Loader {
+            source,
+            messages: vec![],
+        };
+
+        let ini = match Ini::load_from_str(source) {
+            Ok(ini) => ini,
+            Err(e) => {
+                loader.messages.push(ConfigMessage {
+                    severity: Severity::Error,
+                    line: Some(e.line + 1),
+                    text: format!("Can't parse config: {}. Using defaults", e.msg),
+                });
+                return (config, loader.messages);
+            }
+        };
+
+        for (section, properties) in ini.iter() {
+            let section = section.as_ref().map(String::as_str);
+            for (key, value) in properties {
+                match (section, key.as_str()) {
+                    (Some("queue"), "callbacks-per-frame") => {
+                        match loader.parse(section.unwrap(), key, value) {
+                            Some(0) => loader.push(
+                                Severity::Error,
+                                section,
+                                Some(key),
+                                "\"queue.callbacks-per-frame\" should be greater than zero. Using default"
+                                    .to_owned(),
+                            ),
+                            Some(value) => config.callbacks_per_frame = value,
+                            None => {}
+                        }
+                    }
+                    (Some("queue"), "microseconds-delay-between-attempts") => {
+                        if let Some(value) = loader.parse(section.unwrap(), key, value) {
+                            config.microseconds_delay_between_attempts = value;
+                        }
+                    }
+                    (Some("queue"), "frame-budget-microseconds") => {
+                        if let Some(value) = loader.parse(section.unwrap(), key, value) {
+                            config.frame_budget_microseconds = value;
+                        }
+                    }
+                    (Some("network"), "max-rate-limit-hold-seconds") => {
+                        if let Some(value) = loader.parse(section.unwrap(), key, value) {
+                            config.max_rate_limit_hold_seconds = value;
+                        }
+                    }
+                    (Some("network"), "max-concurrent-requests") => {
+                        if let Some(value) = loader.parse(section.unwrap(), key, value) {
+                            config.max_concurrent_requests = value;
+                        }
+                    }
+                    (Some("network"), "max-concurrent-requests-per-host") => {
+                        if let Some(value) = loader.parse(section.unwrap(), key, value) {
+                            config.max_concurrent_requests_per_host = value;
+                        }
+                    }
+                    (Some("log"), "level") => {
+                        if let Some(value) = loader.parse(section.unwrap(), key, value) {
+                            config.log_level = value;
+                        }
+                    }
+                    (Some("plugin-weights"), plugin) => {
+                        match loader.parse(section.unwrap(), key, value) {
+                            Some(0) => loader.push(
+                                Severity::Error,
+                                section,
+                                Some(key),
+                                format!(
+                                    "Weight of the plugin \"{}\" should be greater than zero. Using default",
+                                    plugin
+                                ),
+                            ),
+                            Some(weight) => {
+                                config.plugin_weights.insert(plugin.to_owned(), weight);
+                            }
+                            None => {}
+                        }
+                    }
+                    (Some("rate-limit-groups"), group) => {
+                        if let Some(value) = loader.parse(section.unwrap(), key, value) {
+                            config.rate_limit_groups.insert(group.to_owned(), value);
+                        }
+                    }
+                    (Some(section_name), _) => loader.push(
+                        Severity::Warning,
+                        section,
+                        Some(key),
+                        format!("Unknown key \"{}.{}\"", section_name, key),
+                    ),
+                    (None, _) => loader.push(
+                        Severity::Warning,
+                        section,
+                        Some(key),
+                        format!("Key \"{}\" is outside of any section", key),
+                    ),
+                }
+            }
+
+            match section {
+                Some("queue")
+                | Some("network")
+                | Some("log")
+                | Some("plugin-weights")
+                | Some("rate-limit-groups")
+                | None => {}
+                Some(section_name) => loader.push(
+                    Severity::Warning,
+                    section,
+                    None,
+                    format!("Unknown section [{}]", section_name),
+                ),
+            }
+        }
+
+        loader.messages.sort_by_key(|message| message.line);
+        (config, loader.messages)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults_and_validation() {
+        let (config, messages) = Config::load_from_str("");
+        assert_eq!(config, Config::default());
+        assert!(messages.is_empty());
+
+        let (config, messages) = Config::load_from_str(
+            "[queue]\n\
+             # Comment\n\
+             callbacks-per-frame = 4\n\
+             microseconds-delay-between-attempts = 1000\n\
+             frame-budget-microseconds = 2000\n\
+             [network]\n\
+             max-rate-limit-hold-seconds = 10\n\
+             max-concurrent-requests = 16\n\
+             max-concurrent-requests-per-host = 4\n\
+             [log]\n\
+             level = info\n",
+        );
+        assert_eq!(config.callbacks_per_frame, 4);
+        assert_eq!(config.max_rate_limit_hold_seconds, 10);
+        assert_eq!(config.max_concurrent_requests, 16);
+        assert_eq!(config.max_concurrent_requests_per_host, 4);
+        assert_eq!(config.log_level, LevelFilter::Info);
+        assert_eq!(config.microseconds_delay_between_attempts, 1000);
+        assert_eq!(config.frame_budget_microseconds, 2000);
+        assert!(messages.is_empty());
+
+        let (config, messages) = Config::load_from_str(
+            "[queue]\n\
+             callbacks-per-frame = 0\n\
+             microseconds-delay-between-attempts = fast\n\
+             callback-per-frame = 2\n\
+             [proxy]\n",
+        );
+        assert_eq!(config, Config::default());
+        let lines: Vec<_> = messages
+            .iter()
+            .map(|message| (message.severity, message.line))
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                (Severity::Error, Some(2)),
+                (Severity::Error, Some(3)),
+                (Severity::Warning, Some(4)),
+                (Severity::Warning, Some(5)),
+            ]
+        );
+        assert!(messages[2].to_string().contains("queue.callback-per-frame"));
+    }
+
+    #[test]
+    fn reload() {
+        let mut config = Config::default();
+        let (new, _) = Config::load_from_str("[queue]\ncallbacks-per-frame = 4\n");
+
+        let changes = config.reload(&new);
+        assert_eq!(config, new);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].to_string(), "queue.callbacks-per-frame: 1 -> 4");
+
+        assert!(config.reload(&new).is_empty());
+    }
+
+    #[test]
+    fn plugin_weights() {
+        let (mut config, messages) =
+            Config::load_from_str("[plugin-weights]\nauth.amxx = 4\nstats.amxx = 0\n");
+        assert_eq!(config.plugin_weight("auth.amxx"), 4);
+        assert_eq!(config.plugin_weight("stats.amxx"), 1);
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].line, Some(3));
+
+        let (new, _) = Config::load_from_str("[plugin-weights]\nstats.amxx = 2\n");
+        let changes: Vec<_> = config.reload(&new).iter().map(|c| c.to_string()).collect();
+        assert_eq!(
+            changes,
+            vec![
+                "plugin-weights.auth.amxx: 4 -> 1",
+                "plugin-weights.stats.amxx: 1 -> 2"
+            ]
+        );
+        assert_eq!(config, new);
+    }
+
+    #[test]
+    fn rate_limit_groups() {
+        let (mut config, messages) = Config::load_from_str(
+            "[rate-limit-groups]\nanticheat = 5, 10\nstats = 0, 1\nmaster = 0.5, 1\n",
+        );
+        assert_eq!(
+            config.rate_limit_groups.get("anticheat"),
+            Some(&RateLimitGroup {
+                rate: 5.0,
+                burst: 10
+            })
+        );
+        assert!(!config.rate_limit_groups.contains_key("stats"));
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].line, Some(3));
+
+        let (new, _) = Config::load_from_str("[rate-limit-groups]\nanticheat = 2, 4\n");
+        let changes: Vec<_> = config.reload(&new).iter().map(|c| c.to_string()).collect();
+        assert_eq!(
+            changes,
+            vec![
+                "rate-limit-groups.anticheat: 5, 10 -> 2, 4",
+                "rate-limit-groups.master: 0.5, 1 -> none"
+            ]
+        );
+        assert_eq!(config, new);
+    }
+
+    #[test]
+    fn broken_or_missing_file() {
+        let (config, messages) = Config::load_from_str("[queue]\n= 3\n");
+        assert_eq!(config, Config::default());
+        assert_eq!(messages[0].severity, Severity::Error);
+        assert_eq!(messages[0].line, Some(2));
+
+        let (config, messages) = Config::load_from_file("missing-grip.ini");
+        assert_eq!(config, Config::default());
+        assert_eq!(messages[0].severity, Severity::Warning);
+    }
+}
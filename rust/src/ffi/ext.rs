@@ -69,6 +69,33 @@ macro_rules! try_and_log_ffi {
     };
 }
 
+/// Runs body of the native, so panic doesn't unwind into the AMXX and is reported as native error.
+macro_rules! catch_panic_ffi {
+    ($amx:expr, $default:expr, $body:block) => {
+        match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| $body)) {
+            std::result::Result::Ok(val) => val,
+            std::result::Result::Err(payload) => {
+                log_panic($amx, &panic_message(&*payload));
+                $default
+            }
+        }
+    };
+
+    ($amx:expr, $body:block) => {
+        catch_panic_ffi!($amx, 0, $body)
+    };
+}
+
+pub fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        (*message).to_owned()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "Unknown panic".to_owned()
+    }
+}
+
 pub fn ptr_to_option<T>(ptr: *const T) -> Option<*const T> {
     if ptr.is_null() {
         None
@@ -83,6 +110,53 @@ pub unsafe fn str_from_ptr<'a>(value: *const c_char) -> Result<&'a str> {
         .chain_err(|| "Can't create string from raw pointer.")
 }
 
+pub fn serialize_json(value: &Value, pretty: bool) -> Result<String> {
+    Ok(if pretty {
+        serde_json::to_string_pretty(value)?
+    } else {
+        serde_json::to_string(value)?
+    })
+}
+
+/// Evaluates JSONPath expression, matched values are cloned into a new array.
+pub fn query_json(value: &Value, path: &str) -> Result<Value> {
+    let selected = jsonpath_lib::select(value, path)
+        .map_err(|e| Error::from(format!("Invalid JSONPath `{}`: {}", path, e)))?;
+
+    Ok(Value::Array(selected.into_iter().cloned().collect()))
+}
+
+/// Applies RFC 6902 JSON Patch. Value is left untouched if any of the operations fails.
+pub fn apply_json_patch(value: &mut Value, patch: &Value) -> Result<()> {
+    let operations = match patch {
+        Value::Array(operations) => operations,
+        v => bail!("JSON Patch should be array, got {}", json_type_name(v)),
+    };
+
+    let mut result = value.clone();
+    for (index, operation) in operations.iter().enumerate() {
+        let parsed: json_patch::PatchOperation = serde_json::from_value(operation.clone())
+            .chain_err(|| {
+                format!(
+                    "JSON Patch operation {} (`{}`) is invalid",
+                    index, operation
+                )
+            })?;
+
+        json_patch::patch_unsafe(&mut result, &json_patch::Patch(vec![parsed]))
+            .chain_err(|| format!("JSON Patch operation {} (`{}`) failed", index, operation))?;
+    }
+
+    *value = result;
+
+    Ok(())
+}
+
+/// Generates RFC 6902 JSON Patch which transforms `from` into `to`.
+pub fn diff_json(from: &Value, to: &Value) -> Result<Value> {
+    Ok(serde_json::to_value(json_patch::diff(from, to))?)
+}
+
 macro_rules! try_as_usize {
     ($amx:expr, $size:expr, $error_logger:expr) => {
         try_and_log_ffi!(
@@ -106,6 +180,24 @@ macro_rules! try_as_usize {
     };
 }
 
+macro_rules! try_as_array_index {
+    ($amx:expr, $vec:expr, $index:expr) => {{
+        let index = try_as_usize!($amx, $index);
+        let len = $vec.len();
+        try_and_log_ffi!(
+            $amx,
+            if index < len {
+                Ok(index)
+            } else {
+                Err(ffi_error(format!(
+                    "Index {} is out of bounds. Array size is {}.",
+                    index, len
+                )))
+            }
+        )
+    }};
+}
+
 macro_rules! copy_unsafe_string {
     ($amx:expr, $dest:expr, $source:expr, $size:expr, $error_logger:expr) => {{
         let source = format!("{}\0", $source);
@@ -139,6 +231,26 @@ macro_rules! unconditionally_log_error {
     };
 }
 
+macro_rules! try_to_get_response {
+    ($amx:expr) => {{
+        let response: &crate::networking_queue::Response = match try_and_log_ffi!(
+            $amx,
+            get_module()
+                .current_response
+                .as_ref()
+                .chain_err(|| ffi_error("No active response at this time"))
+        ) {
+            Ok(response) => response,
+            Err(_) => unconditionally_log_error!(
+                $amx,
+                ffi_error("Error/Cancellation/Timeout occurred for this response.")
+            ),
+        };
+
+        response
+    }};
+}
+
 macro_rules! try_to_get_json_value {
     ($amx:expr, $value:expr) => {{
         let value: &Value = try_and_log_ffi!(
@@ -167,6 +279,22 @@ macro_rules! try_to_get_json_value_mut {
     }};
 }
 
+macro_rules! try_to_set_json_object_value {
+    ($amx:expr, $object:expr, $name:expr, $value:expr, $dot_notation:expr) => {{
+        let value: Value = $value;
+        try_and_log_ffi!(
+            $amx,
+            try_to_get_json_value_mut!($amx, $object).set_selective(
+                try_and_log_ffi!($amx, str_from_ptr($name)),
+                value,
+                $dot_notation
+            )
+        );
+
+        1
+    }};
+}
+
 macro_rules! try_to_get_json_object_value {
     ($amx:expr, $object:expr, $name:expr, $dot_notation:expr) => {{
         try_and_log_ffi!(
@@ -177,6 +305,29 @@ macro_rules! try_to_get_json_object_value {
     }};
 }
 
+macro_rules! try_to_get_json_pointer_value {
+    ($amx:expr, $value:expr, $pointer:expr) => {{
+        try_and_log_ffi!(
+            $amx,
+            try_to_get_json_value!($amx, $value)
+                .pointer_safe(try_and_log_ffi!($amx, str_from_ptr($pointer)))
+        )
+    }};
+}
+
+macro_rules! try_to_set_json_pointer_value {
+    ($amx:expr, $target:expr, $pointer:expr, $value:expr) => {{
+        let value: Value = $value;
+        try_and_log_ffi!(
+            $amx,
+            try_to_get_json_value_mut!($amx, $target)
+                .pointer_set(try_and_log_ffi!($amx, str_from_ptr($pointer)), value)
+        );
+
+        1
+    }};
+}
+
 pub trait ValueExt<'a>: std::ops::Index<&'a str, Output = Value> {
     fn dot_index_safe(&self, name: &str) -> Result<&Value>;
     fn dot_index_safe_mut(&mut self, name: &str) -> Result<&mut Value>;
@@ -184,18 +335,265 @@ pub trait ValueExt<'a>: std::ops::Index<&'a str, Output = Value> {
     fn index_selective_safe(&self, name: &'a str, dot_notation: bool) -> Result<&Value>;
     fn index_selective_safe_mut(&mut self, name: &'a str, dot_notation: bool)
         -> Result<&mut Value>;
+
+    fn set_selective(&mut self, name: &'a str, value: Value, dot_notation: bool) -> Result<()>;
+    fn remove_selective(&mut self, name: &'a str, dot_notation: bool) -> Result<Value>;
+
+    /// RFC 6901 JSON Pointer lookup, errors describe the segment which failed to resolve.
+    fn pointer_safe(&self, pointer: &str) -> Result<&Value>;
+    fn pointer_safe_mut(&mut self, pointer: &str) -> Result<&mut Value>;
+
+    /// Sets value at the JSON Pointer. Parent must exist, `-` appends to the array.
+    fn pointer_set(&mut self, pointer: &str, value: Value) -> Result<()>;
+}
+
+pub fn json_type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// Splits JSON Pointer into unescaped reference tokens.
+fn split_json_pointer(pointer: &str) -> Result<Vec<String>> {
+    if pointer.is_empty() {
+        return Ok(vec![]);
+    }
+
+    if !pointer.starts_with('/') {
+        bail!("JSON pointer `{}` must be empty or start with `/`", pointer);
+    }
+
+    pointer
+        .split('/')
+        .skip(1)
+        .enumerate()
+        .map(|(position, token)| {
+            let mut segment = String::with_capacity(token.len());
+            let mut chars = token.chars();
+            while let Some(c) = chars.next() {
+                match c {
+                    '~' => match chars.next() {
+                        Some('0') => segment.push('~'),
+                        Some('1') => segment.push('/'),
+                        _ => bail!(
+                            "JSON pointer `{}` has invalid escape sequence in segment {} (`{}`)",
+                            pointer,
+                            position + 1,
+                            token
+                        ),
+                    },
+                    _ => segment.push(c),
+                }
+            }
+
+            Ok(segment)
+        })
+        .collect()
+}
+
+/// Converts key name (optionally in dot notation) into JSON Pointer.
+pub fn selective_to_pointer(name: &str, dot_notation: bool) -> Result<String> {
+    let segments = if dot_notation {
+        split_dot_path(name)?
+    } else {
+        vec![name.to_owned()]
+    };
+
+    Ok(segments
+        .iter()
+        .map(|segment| format!("/{}", segment.replace('~', "~0").replace('/', "~1")))
+        .collect())
+}
+
+/// Parses array index token as described in RFC 6901, leading zeros are not allowed.
+fn parse_pointer_index(segment: &str, len: usize) -> std::result::Result<usize, String> {
+    if segment == "-" {
+        return Err(format!("`-` refers past the end of array (length {})", len));
+    }
+
+    if segment.is_empty()
+        || !segment.bytes().all(|b| b.is_ascii_digit())
+        || (segment.len() > 1 && segment.starts_with('0'))
+    {
+        return Err("array index is not valid".to_owned());
+    }
+
+    match segment.parse::<usize>() {
+        Ok(index) if index < len => Ok(index),
+        _ => Err(format!("array index is out of bounds (length {})", len)),
+    }
+}
+
+fn pointer_segment_error(pointer: &str, position: usize, segment: &str, reason: &str) -> Error {
+    format!(
+        "Can't resolve JSON pointer `{}` at segment {} (`{}`): {}",
+        pointer,
+        position + 1,
+        segment,
+        reason
+    )
+    .into()
+}
+
+fn pointer_step<'v>(value: &'v Value, segment: &str) -> std::result::Result<&'v Value, String> {
+    match value {
+        Value::Object(m) => m
+            .get(segment)
+            .ok_or_else(|| "object doesn't contain such key".to_owned()),
+        Value::Array(v) => Ok(&v[parse_pointer_index(segment, v.len())?]),
+        v => Err(format!("{} is not object or array", json_type_name(v))),
+    }
+}
+
+fn pointer_step_mut<'v>(
+    value: &'v mut Value,
+    segment: &str,
+) -> std::result::Result<&'v mut Value, String> {
+    match value {
+        Value::Object(m) => m
+            .get_mut(segment)
+            .ok_or_else(|| "object doesn't contain such key".to_owned()),
+        Value::Array(v) => {
+            let index = parse_pointer_index(segment, v.len())?;
+            Ok(&mut v[index])
+        }
+        v => Err(format!("{} is not object or array", json_type_name(v))),
+    }
+}
+
+/// Splits dot notation path into segments, `\.` and `\\` are unescaped into `.` and `\`.
+/// Other backslashes are kept as is, so keys with backslashes are reachable without escaping.
+fn split_dot_path(name: &str) -> Result<Vec<String>> {
+    let mut segments = vec![];
+    let mut segment = String::new();
+    let mut chars = name.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some(escaped @ '.') | Some(escaped @ '\\') => segment.push(escaped),
+                Some(other) => {
+                    segment.push('\\');
+                    segment.push(other);
+                }
+                None => segment.push('\\'),
+            },
+            '.' => {
+                if segment.is_empty() {
+                    bail!("Double/Empty separator in `{}`", name);
+                }
+
+                segments.push(std::mem::take(&mut segment));
+            }
+            _ => segment.push(c),
+        }
+    }
+
+    if segment.is_empty() {
+        bail!("Double/Empty separator in `{}`", name);
+    }
+
+    segments.push(segment);
+
+    Ok(segments)
+}
+
+/// Splits path into the parent segments and the last key.
+fn split_parent_path(name: &str, dot_notation: bool) -> Result<(Vec<String>, String)> {
+    if dot_notation {
+        let mut segments = split_dot_path(name)?;
+        let key = segments.pop().unwrap();
+        Ok((segments, key))
+    } else {
+        Ok((vec![], name.to_owned()))
+    }
+}
+
+/// Parses array index segment. Only plain decimal digits are accepted.
+fn parse_array_index(segment: &str, len: usize) -> Result<usize> {
+    if segment.is_empty() || !segment.bytes().all(|b| b.is_ascii_digit()) {
+        bail!(
+            "Can't index json array using `{}`, because it is not an index",
+            segment
+        );
+    }
+
+    match segment.parse::<usize>() {
+        Ok(index) if index < len => Ok(index),
+        _ => bail!(
+            "Can't index json array using `{}`, index is out of bounds (length {})",
+            segment,
+            len
+        ),
+    }
+}
+
+fn index_segment<'v>(value: &'v Value, segment: &str) -> Result<&'v Value> {
+    match value {
+        Value::Object(m) => m.get(segment).chain_err(|| {
+            format!(
+                "Can't index json using `{}`, because json doesn't contain it",
+                segment
+            )
+        }),
+        Value::Array(v) => Ok(&v[parse_array_index(segment, v.len())?]),
+        _ => bail!(
+            "Can't index json using `{}` json stops is not object or array.",
+            segment
+        ),
+    }
+}
+
+fn index_segment_mut<'v>(value: &'v mut Value, segment: &str) -> Result<&'v mut Value> {
+    match value {
+        Value::Object(m) => m.get_mut(segment).chain_err(|| {
+            format!(
+                "Can't index json using `{}`, because json doesn't contain it",
+                segment
+            )
+        }),
+        Value::Array(v) => {
+            let index = parse_array_index(segment, v.len())?;
+            Ok(&mut v[index])
+        }
+        _ => bail!(
+            "Can't index json using `{}` json stops is not object or array.",
+            segment
+        ),
+    }
+}
+
+/// Follows segments, missing intermediate objects are created.
+fn index_segments_or_insert_mut<'v>(
+    value: &'v mut Value,
+    segments: &[String],
+) -> Result<&'v mut Value> {
+    let mut it = value;
+    for segment in segments {
+        it = match it {
+            Value::Object(m) => m
+                .entry(segment.clone())
+                .or_insert_with(|| Value::Object(serde_json::Map::new())),
+            Value::Array(_) => index_segment_mut(it, segment)?,
+            _ => bail!(
+                "Can't index json using `{}` json stops is not object or array.",
+                segment
+            ),
+        };
+    }
+
+    Ok(it)
 }
 
 impl<'a> ValueExt<'a> for Value {
     fn dot_index_safe(&self, name: &str) -> Result<&Value> {
         let mut it = self;
-        for element in name.split('.') {
-            if element.is_empty() {
-                bail!("Double/Empty separator in `{}`", name);
-            }
-
-            // Same as bounds checked index.
-            it = it.index_selective_safe(element, false)?;
+        for segment in split_dot_path(name)? {
+            it = index_segment(it, &segment)?;
         }
 
         Ok(it)
@@ -203,13 +601,8 @@ impl<'a> ValueExt<'a> for Value {
 
     fn dot_index_safe_mut(&mut self, name: &str) -> Result<&mut Value> {
         let mut it = self;
-        for element in name.split('.') {
-            if element.is_empty() {
-                bail!("Double/Empty separator in `{}`", name);
-            }
-
-            // Same as bounds checked index.
-            it = it.index_selective_safe_mut(element, false)?;
+        for segment in split_dot_path(name)? {
+            it = index_segment_mut(it, &segment)?;
         }
 
         Ok(it)
@@ -264,6 +657,132 @@ impl<'a> ValueExt<'a> for Value {
             }
         }
     }
+
+    fn set_selective(&mut self, name: &'a str, value: Value, dot_notation: bool) -> Result<()> {
+        // Existing value is replaced in place, so position of the key is kept.
+        if let Ok(existing) = self.index_selective_safe_mut(name, dot_notation) {
+            *existing = value;
+            return Ok(());
+        }
+
+        let (parent_path, key) = split_parent_path(name, dot_notation)?;
+        let parent = index_segments_or_insert_mut(self, &parent_path)?;
+
+        match parent {
+            Value::Object(m) => {
+                m.insert(key, value);
+                Ok(())
+            }
+            Value::Array(_) if dot_notation => {
+                *index_segment_mut(parent, &key)? = value;
+                Ok(())
+            }
+            _ => bail!(
+                "Can't set json value using `{}` json stops is not object.",
+                name
+            ),
+        }
+    }
+
+    fn remove_selective(&mut self, name: &'a str, dot_notation: bool) -> Result<Value> {
+        let (parent_path, key) = split_parent_path(name, dot_notation)?;
+
+        let mut parent = self;
+        for segment in &parent_path {
+            parent = index_segment_mut(parent, segment)?;
+        }
+
+        match parent {
+            Value::Object(m) => {
+                if !m.contains_key(&key) {
+                    bail!(
+                        "Can't remove json value using `{}`, because json doesn't contain it",
+                        name
+                    )
+                }
+
+                // Map::remove swaps removed entry with the last one, so order of keys is rebuilt.
+                let mut removed = None;
+                for (k, v) in std::mem::take(m) {
+                    if k == key {
+                        removed = Some(v);
+                    } else {
+                        m.insert(k, v);
+                    }
+                }
+
+                Ok(removed.unwrap())
+            }
+            Value::Array(v) if dot_notation => {
+                let index = parse_array_index(&key, v.len())?;
+                Ok(v.remove(index))
+            }
+            _ => bail!(
+                "Can't remove json value using `{}` json stops is not object.",
+                name
+            ),
+        }
+    }
+
+    fn pointer_safe(&self, pointer: &str) -> Result<&Value> {
+        let mut it = self;
+        for (position, segment) in split_json_pointer(pointer)?.iter().enumerate() {
+            it = pointer_step(it, segment)
+                .map_err(|reason| pointer_segment_error(pointer, position, segment, &reason))?;
+        }
+
+        Ok(it)
+    }
+
+    fn pointer_safe_mut(&mut self, pointer: &str) -> Result<&mut Value> {
+        let mut it = self;
+        for (position, segment) in split_json_pointer(pointer)?.iter().enumerate() {
+            it = pointer_step_mut(it, segment)
+                .map_err(|reason| pointer_segment_error(pointer, position, segment, &reason))?;
+        }
+
+        Ok(it)
+    }
+
+    fn pointer_set(&mut self, pointer: &str, value: Value) -> Result<()> {
+        let mut segments = split_json_pointer(pointer)?;
+        let key = match segments.pop() {
+            Some(key) => key,
+            None => {
+                *self = value;
+                return Ok(());
+            }
+        };
+
+        let mut parent = self;
+        for (position, segment) in segments.iter().enumerate() {
+            parent = pointer_step_mut(parent, segment)
+                .map_err(|reason| pointer_segment_error(pointer, position, segment, &reason))?;
+        }
+
+        match parent {
+            Value::Object(m) => {
+                m.insert(key, value);
+            }
+            Value::Array(v) if key == "-" => v.push(value),
+            Value::Array(v) => {
+                let index = parse_pointer_index(&key, v.len()).map_err(|reason| {
+                    pointer_segment_error(pointer, segments.len(), &key, &reason)
+                })?;
+                v[index] = value;
+            }
+            v => {
+                return Err(pointer_segment_error(
+                    pointer,
+                    segments.len(),
+                    &key,
+                    &format!("{} is not object or array", json_type_name(v)),
+                ))
+            }
+        }
+
+        Ok(())
+    }
 }
 
 #[allow(unused_imports)]
@@ -295,6 +814,70 @@ mod tests {
         }
     }
 
+    #[test]
+    fn serialize_json_test() {
+        let json = json!({"a": [1, 2]});
+
+        assert_eq!(serialize_json(&json, false).unwrap(), r#"{"a":[1,2]}"#);
+        assert_eq!(
+            serialize_json(&json, true).unwrap(),
+            "{\n  \"a\": [\n    1,\n    2\n  ]\n}"
+        );
+    }
+
+    #[test]
+    fn query_json_test() {
+        let json = json!({
+            "players": [
+                {"id": 1, "team": "CT"},
+                {"id": 2, "team": "T"},
+                {"id": 3, "team": "CT"}
+            ]
+        });
+
+        assert_eq!(
+            query_json(&json, "$.players[?(@.team=='CT')].id").unwrap(),
+            json!([1, 3])
+        );
+        assert_eq!(query_json(&json, "$.missing").unwrap(), json!([]));
+        assert!(query_json(&json, "$.players[?(").is_err());
+    }
+
+    #[test]
+    fn json_patch_test() {
+        let mut json = json!({"a": 1, "b": [1, 2]});
+
+        apply_json_patch(
+            &mut json,
+            &json!([
+                {"op": "replace", "path": "/a", "value": 2},
+                {"op": "add", "path": "/b/-", "value": 3},
+                {"op": "test", "path": "/a", "value": 2}
+            ]),
+        )
+        .unwrap();
+        assert_eq!(json, json!({"a": 2, "b": [1, 2, 3]}));
+
+        let error = apply_json_patch(
+            &mut json,
+            &json!([
+                {"op": "remove", "path": "/a"},
+                {"op": "test", "path": "/b/0", "value": 5}
+            ]),
+        )
+        .unwrap_err();
+        assert!(format!("{}", error).starts_with("JSON Patch operation 1 ("));
+        assert_eq!(json, json!({"a": 2, "b": [1, 2, 3]}));
+
+        assert!(apply_json_patch(&mut json, &json!([{"op": "unknown"}])).is_err());
+        assert!(apply_json_patch(&mut json, &json!({})).is_err());
+
+        let to = json!({"b": [1, 2], "c": {"d": true}});
+        let mut from = json.clone();
+        apply_json_patch(&mut from, &diff_json(&json, &to).unwrap()).unwrap();
+        assert_eq!(from, to);
+    }
+
     #[test]
     fn dot_index_safe() {
         let mut json = json!({
@@ -351,4 +934,202 @@ mod tests {
         assert!(json.index_selective_safe_mut("a.b.c", false).is_err());
     }
 
+    #[test]
+    fn set_selective() {
+        let mut json = json!({
+            "a": {
+                "b": 123
+            },
+            "c": 1
+        });
+
+        json.set_selective("d", json!("str"), false).unwrap();
+        json.set_selective("a.b", json!(null), true).unwrap();
+        json.set_selective("x.y.z", json!(true), true).unwrap();
+        json.set_selective("e.f", json!(5), false).unwrap();
+
+        assert_eq!(
+            json,
+            json!({
+                "a": {
+                    "b": null
+                },
+                "c": 1,
+                "d": "str",
+                "x": {
+                    "y": {
+                        "z": true
+                    }
+                },
+                "e.f": 5
+            })
+        );
+
+        assert!(json.set_selective("c.d", json!(1), true).is_err());
+        assert!(json.set_selective("a..b", json!(1), true).is_err());
+        assert!(json.set_selective("a.", json!(1), true).is_err());
+        assert!(json!([]).set_selective("a", json!(1), false).is_err());
+    }
+
+    #[test]
+    fn remove_selective() {
+        let mut json = json!({
+            "a": {
+                "b": 123,
+                "c": 1
+            },
+            "d.e": 2
+        });
+
+        assert_eq!(json.remove_selective("a.b", true).unwrap(), json!(123));
+        assert_eq!(json.remove_selective("d.e", false).unwrap(), json!(2));
+        assert!(json.remove_selective("a.b", true).is_err());
+        assert!(json.remove_selective("x.b", true).is_err());
+        assert!(json.remove_selective("a.c.d", true).is_err());
+
+        assert_eq!(json, json!({"a": {"c": 1}}));
+    }
+
+    #[test]
+    fn dot_notation_arrays() {
+        let mut json = json!({
+            "players": [
+                {"name": "a"},
+                {"name": "b"}
+            ]
+        });
+
+        assert_eq!(json.dot_index_safe("players.1.name").unwrap(), &json!("b"));
+        assert!(json.dot_index_safe("players.2.name").is_err());
+        assert!(json.dot_index_safe("players.-1").is_err());
+        assert!(json.dot_index_safe("players.+1").is_err());
+        assert!(json.dot_index_safe("players.x").is_err());
+
+        *json.dot_index_safe_mut("players.0.name").unwrap() = json!("c");
+        json.set_selective("players.1", json!(null), true).unwrap();
+        json.set_selective("players.0.team.id", json!(2), true)
+            .unwrap();
+        assert!(json.set_selective("players.2", json!(1), true).is_err());
+
+        assert_eq!(
+            json,
+            json!({"players": [{"name": "c", "team": {"id": 2}}, null]})
+        );
+
+        assert_eq!(
+            json.remove_selective("players.0", true).unwrap(),
+            json!({"name": "c", "team": {"id": 2}})
+        );
+        assert!(json.remove_selective("players.1", true).is_err());
+        assert_eq!(json, json!({"players": [null]}));
+    }
+
+    #[test]
+    fn dot_notation_escaping() {
+        let mut json = json!({
+            "a.b": {
+                "c": 1
+            },
+            "d\\": 2,
+            "e\\f": {
+                "g\\": 4
+            }
+        });
+
+        assert_eq!(json.dot_index_safe(r"a\.b.c").unwrap(), &json!(1));
+        assert_eq!(json.dot_index_safe(r"d\\").unwrap(), &json!(2));
+        assert!(json.dot_index_safe(r"a\.b\.c").is_err());
+        assert!(json.dot_index_safe(r"a\b").is_err());
+
+        // Unknown escapes are literal backslashes.
+        assert_eq!(json.dot_index_safe(r"d\").unwrap(), &json!(2));
+        assert_eq!(json.dot_index_safe(r"e\f.g\").unwrap(), &json!(4));
+
+        json.set_selective(r"x\.y.z", json!(3), true).unwrap();
+        assert_eq!(json["x.y"]["z"], json!(3));
+
+        assert_eq!(json.remove_selective(r"a\.b.c", true).unwrap(), json!(1));
+        assert_eq!(json["a.b"], json!({}));
+    }
+
+    #[test]
+    fn json_pointer() {
+        let mut json = json!({
+            "players": [
+                {"name": "a"},
+                {"name": "b"}
+            ],
+            "a/b": {"c~d": 1}
+        });
+
+        assert_eq!(json.pointer_safe("").unwrap(), &json.clone());
+        assert_eq!(json.pointer_safe("/players/1/name").unwrap(), &json!("b"));
+        assert_eq!(json.pointer_safe("/a~1b/c~0d").unwrap(), &json!(1));
+
+        let error = |json: &Value, pointer| format!("{}", json.pointer_safe(pointer).unwrap_err());
+        assert_eq!(
+            error(&json, "/players/2/name"),
+            "Can't resolve JSON pointer `/players/2/name` at segment 2 (`2`): \
+             array index is out of bounds (length 2)"
+        );
+        assert_eq!(
+            error(&json, "/players/01"),
+            "Can't resolve JSON pointer `/players/01` at segment 2 (`01`): \
+             array index is not valid"
+        );
+        assert_eq!(
+            error(&json, "/players/0/name/x"),
+            "Can't resolve JSON pointer `/players/0/name/x` at segment 4 (`x`): \
+             string is not object or array"
+        );
+        assert_eq!(
+            error(&json, "/missing"),
+            "Can't resolve JSON pointer `/missing` at segment 1 (`missing`): \
+             object doesn't contain such key"
+        );
+        assert!(json.pointer_safe("players").is_err());
+        assert!(json.pointer_safe("/a~2b").is_err());
+        assert!(json.pointer_safe("/players/-").is_err());
+
+        json.pointer_set("/players/0/name", json!("c")).unwrap();
+        json.pointer_set("/players/1", json!(null)).unwrap();
+        json.pointer_set("/players/-", json!(3)).unwrap();
+        json.pointer_set("/a~1b/e", json!(true)).unwrap();
+        assert!(json.pointer_set("/players/4", json!(1)).is_err());
+        assert!(json.pointer_set("/x/y", json!(1)).is_err());
+
+        assert_eq!(
+            json,
+            json!({
+                "players": [{"name": "c"}, null, 3],
+                "a/b": {"c~d": 1, "e": true}
+            })
+        );
+
+        json.pointer_set("", json!([])).unwrap();
+        assert_eq!(json, json!([]));
+    }
+
+    #[test]
+    fn selective_to_pointer_test() {
+        assert_eq!(selective_to_pointer("a.b", false).unwrap(), "/a.b");
+        assert_eq!(
+            selective_to_pointer(r"a\.b.c/d~.0", true).unwrap(),
+            "/a.b/c~1d~0/0"
+        );
+        assert!(selective_to_pointer("a..b", true).is_err());
+    }
+
+    #[test]
+    fn object_keys_order() {
+        let mut json = json!({});
+        for key in &["z", "a", "m", "b"] {
+            json.set_selective(key, json!(key), false).unwrap();
+        }
+
+        json.remove_selective("a", false).unwrap();
+
+        let keys: Vec<_> = json.as_object().unwrap().keys().collect();
+        assert_eq!(keys, vec!["z", "m", "b"]);
+    }
 }
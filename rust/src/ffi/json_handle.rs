@@ -0,0 +1,419 @@
+/*
+ * gRIP
+ * Copyright (c) 2018 Alik Aslanyan <cplusplus256@gmail.com>
+ *
+ *
+ *    This program is free software; you can redistribute it and/or modify it
+ *    under the terms of the GNU General Public License as published by the
+ *    Free Software Foundation; either version 3 of the License, or (at
+ *    your option) any later version.
+ *
+ *    This program is distributed in the hope that it will be useful, but
+ *    WITHOUT ANY WARRANTY; without even the implied warranty of
+ *    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+ *    General Public License for more details.
+ *
+ *    You should have received a copy of the GNU General Public License
+ *    along with this program; if not, write to the Free Software Foundation,
+ *    Inc., 59 Temple Place, Suite 330, Boston, MA  02111-1307  USA
+ *
+ *    In addition, as a special exception, the author gives permission to
+ *    link the code of this program with the Half-Life Game Engine ("HL
+ *    Engine") and Modified Game Libraries ("MODs") developed by Valve,
+ *    L.L.C ("Valve").  You must obey the GNU General Public License in all
+ *    respects for all of the code used other than the HL Engine and MODs
+ *    from Valve.  If you modify this file, you may extend this exception
+ *    to your version of the file, but you are not obligated to do so.  If
+ *    you do not wish to do so, delete this exception statement from your
+ *    version.
+ *
+ */
+
+use super::ext::ValueExt;
+use super::Cell;
+use crate::cell_map::{CellMap, HandleInfo, HandleKind};
+use crate::errors::*;
+use serde_json::Value;
+use std::os::raw::c_void;
+use std::rc::Rc;
+
+pub enum JsonHandle {
+    Owned(Value),
+    /// Immutable document, which is shared between handles instead of being cloned.
+    Frozen(Rc<Value>),
+    /// Refers to the JSON Pointer inside of the owned or frozen parent handle.
+    /// Children of children are flattened, so parent is never a child.
+    Child {
+        parent: Cell,
+        pointer: String,
+    },
+}
+
+/// Handles created by the plugin while the scope is open, they are destroyed when it ends.
+struct Scope {
+    owner: *const c_void,
+    handles: Vec<Cell>,
+    /// Opened by the module around request callback, can't be ended by plugins.
+    automatic: bool,
+}
+
+/// Storage of JSON handles which resolves child handles through their parents.
+pub struct JsonHandles {
+    inner: CellMap<JsonHandle>,
+    scopes: Vec<Scope>,
+}
+
+impl JsonHandles {
+    pub fn new() -> JsonHandles {
+        JsonHandles {
+            inner: CellMap::new(HandleKind::JSONValue),
+            scopes: vec![],
+        }
+    }
+
+    /// Every handle is inserted here, so it is tracked by the innermost scope of its owner.
+    fn insert(&mut self, owner: *const c_void, handle: JsonHandle) -> Result<Cell> {
+        let id = self.inner.insert_with_unique_id(owner, handle)?;
+        if let Some(scope) = self
+            .scopes
+            .iter_mut()
+            .rev()
+            .find(|scope| scope.owner == owner)
+        {
+            scope.handles.push(id);
+        }
+
+        Ok(id)
+    }
+
+    pub fn insert_with_unique_id(&mut self, owner: *const c_void, value: Value) -> Result<Cell> {
+        self.insert(owner, JsonHandle::Owned(value))
+    }
+
+    /// Returns number of scopes open by the `owner` including the new one.
+    pub fn begin_scope(&mut self, owner: *const c_void, automatic: bool) -> usize {
+        self.scopes.push(Scope {
+            owner,
+            handles: vec![],
+            automatic,
+        });
+
+        self.scopes
+            .iter()
+            .filter(|scope| scope.owner == owner)
+            .count()
+    }
+
+    fn innermost_scope(&self, owner: *const c_void) -> Option<usize> {
+        self.scopes.iter().rposition(|scope| scope.owner == owner)
+    }
+
+    /// Ends the innermost scope of the `owner` and returns count of destroyed handles.
+    /// Ending automatic scope also ends all scopes which plugin left open inside of it.
+    pub fn end_scope(&mut self, owner: *const c_void, automatic: bool) -> Result<usize> {
+        match self
+            .innermost_scope(owner)
+            .map(|position| &self.scopes[position])
+        {
+            Some(scope) if scope.automatic && !automatic => {
+                bail!("Can't end scope which was opened by the module")
+            }
+            None => bail!("There is no open scope"),
+            _ => (),
+        }
+
+        let mut destroyed = 0;
+        while let Some(position) = self.innermost_scope(owner) {
+            let scope = self.scopes.remove(position);
+            destroyed += scope
+                .handles
+                .iter()
+                .filter(|id| self.inner.remove_with_id(**id).is_ok())
+                .count();
+
+            if !automatic || scope.automatic {
+                break;
+            }
+        }
+
+        Ok(destroyed)
+    }
+
+    /// Moves handle to the enclosing scope, or makes it persistent if there is none.
+    pub fn promote(&mut self, id: Cell) -> Result<()> {
+        self.inner.get_with_id(id)?;
+
+        let position = self
+            .scopes
+            .iter()
+            .rposition(|scope| scope.handles.contains(&id))
+            .chain_err(|| format!("JSON value handle {} doesn't belong to any scope", id))?;
+
+        let owner = self.scopes[position].owner;
+        self.scopes[position].handles.retain(|handle| *handle != id);
+        if let Some(enclosing) = self.scopes[..position]
+            .iter_mut()
+            .rev()
+            .find(|scope| scope.owner == owner)
+        {
+            enclosing.handles.push(id);
+        }
+
+        Ok(())
+    }
+
+    /// Creates handle which reads and writes through to the `pointer` inside of `id`.
+    /// Array indices of the pointer aren't updated, when elements are inserted or removed.
+    pub fn insert_child(&mut self, owner: *const c_void, id: Cell, pointer: &str) -> Result<Cell> {
+        let (parent, pointer) = match self.inner.get_with_id(id)? {
+            JsonHandle::Child {
+                parent,
+                pointer: parent_pointer,
+            } => (*parent, format!("{}{}", parent_pointer, pointer)),
+            _ => (id, pointer.to_owned()),
+        };
+
+        // Path must exist at the time of creation.
+        self.get_with_id(parent)?.pointer_safe(&pointer)?;
+
+        self.insert(owner, JsonHandle::Child { parent, pointer })
+    }
+
+    /// Makes the handle immutable, so it can be shared using `retain`.
+    pub fn freeze(&mut self, id: Cell) -> Result<()> {
+        let handle = self.inner.get_mut_with_id(id)?;
+
+        *handle = match std::mem::replace(handle, JsonHandle::Owned(Value::Null)) {
+            JsonHandle::Owned(value) => JsonHandle::Frozen(Rc::new(value)),
+            frozen @ JsonHandle::Frozen(_) => frozen,
+            child @ JsonHandle::Child { .. } => {
+                *handle = child;
+                bail!("Child handle {} can't be frozen", id);
+            }
+        };
+
+        Ok(())
+    }
+
+    /// Creates a new handle which shares the frozen document.
+    pub fn retain(&mut self, owner: *const c_void, id: Cell) -> Result<Cell> {
+        match self.inner.get_with_id(id)? {
+            JsonHandle::Frozen(value) => {
+                let value = Rc::clone(value);
+                self.insert(owner, JsonHandle::Frozen(value))
+            }
+            _ => bail!("JSON value handle {} is not frozen", id),
+        }
+    }
+
+    /// Returns root handle of the child, which must be owned or frozen.
+    fn get_parent(&self, parent: Cell, id: Cell) -> Result<&JsonHandle> {
+        self.inner
+            .get_with_id(parent)
+            .chain_err(|| format!("Parent {} of child handle {} was destroyed", parent, id))
+    }
+
+    pub fn is_frozen(&self, id: Cell) -> Result<bool> {
+        Ok(match self.inner.get_with_id(id)? {
+            JsonHandle::Frozen(_) => true,
+            JsonHandle::Child { parent, .. } => {
+                matches!(self.get_parent(*parent, id)?, JsonHandle::Frozen(_))
+            }
+            JsonHandle::Owned(_) => false,
+        })
+    }
+
+    pub fn get_with_id(&self, id: Cell) -> Result<&Value> {
+        match self.inner.get_with_id(id)? {
+            JsonHandle::Owned(value) => Ok(value),
+            JsonHandle::Frozen(value) => Ok(value),
+            JsonHandle::Child { parent, pointer } => {
+                let value = match self.get_parent(*parent, id)? {
+                    JsonHandle::Owned(value) => value,
+                    JsonHandle::Frozen(value) => value,
+                    JsonHandle::Child { .. } => unreachable!(),
+                };
+
+                value.pointer_safe(pointer).chain_err(|| {
+                    format!(
+                        "Child handle {} refers to the path which no longer exists in parent {}",
+                        id, parent
+                    )
+                })
+            }
+        }
+    }
+
+    pub fn get_mut_with_id(&mut self, id: Cell) -> Result<&mut Value> {
+        let (parent, pointer) = match self.inner.get_with_id(id)? {
+            JsonHandle::Child { parent, pointer } => {
+                self.get_parent(*parent, id)?;
+                (*parent, Some(pointer.clone()))
+            }
+            _ => (id, None),
+        };
+
+        match (self.inner.get_mut_with_id(parent)?, pointer) {
+            (JsonHandle::Owned(value), None) => Ok(value),
+            (JsonHandle::Owned(value), Some(pointer)) => {
+                value.pointer_safe_mut(&pointer).chain_err(|| {
+                    format!(
+                        "Child handle {} refers to the path which no longer exists in parent {}",
+                        id, parent
+                    )
+                })
+            }
+            (JsonHandle::Frozen(_), _) => {
+                bail!("JSON value handle {} is frozen and can't be modified", id)
+            }
+            (JsonHandle::Child { .. }, _) => unreachable!(),
+        }
+    }
+
+    pub fn remove_with_id(&mut self, id: Cell) -> Result<JsonHandle> {
+        self.inner.remove_with_id(id)
+    }
+
+    pub fn remove_owned_by(&mut self, owner: *const c_void) -> usize {
+        self.inner.remove_owned_by(owner)
+    }
+
+    pub fn infos(&self) -> impl Iterator<Item = (Cell, &HandleInfo)> {
+        self.inner.infos()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::ptr::null;
+
+    #[test]
+    fn child_writes_through() {
+        let mut handles = JsonHandles::new();
+        let root = handles
+            .insert_with_unique_id(null(), json!({"a": {"b": [1, 2]}}))
+            .unwrap();
+
+        let a = handles.insert_child(null(), root, "/a").unwrap();
+        let b = handles.insert_child(null(), a, "/b").unwrap();
+        assert!(handles.insert_child(null(), a, "/missing").is_err());
+
+        *handles.get_mut_with_id(b).unwrap() = json!("changed");
+        assert_eq!(
+            handles.get_with_id(root).unwrap(),
+            &json!({"a": {"b": "changed"}})
+        );
+        assert_eq!(handles.get_with_id(a).unwrap(), &json!({"b": "changed"}));
+
+        handles
+            .get_mut_with_id(a)
+            .unwrap()
+            .remove_selective("b", false)
+            .unwrap();
+        assert!(handles.get_with_id(b).is_err());
+        assert!(handles.get_mut_with_id(b).is_err());
+        assert!(handles.get_with_id(a).is_ok());
+
+        handles.remove_with_id(root).unwrap();
+        assert!(handles.get_with_id(a).is_err());
+        assert!(handles.get_mut_with_id(a).is_err());
+    }
+
+    #[test]
+    fn scopes() {
+        let mut handles = JsonHandles::new();
+        let persistent = handles.insert_with_unique_id(null(), json!(1)).unwrap();
+        assert!(handles.end_scope(null(), false).is_err());
+
+        assert_eq!(handles.begin_scope(null(), true), 1);
+        let promoted = handles.insert_with_unique_id(null(), json!(2)).unwrap();
+        let destroyed = handles.insert_with_unique_id(null(), json!(3)).unwrap();
+        assert!(handles.end_scope(null(), false).is_err());
+
+        assert_eq!(handles.begin_scope(null(), false), 2);
+        let nested = handles.insert_with_unique_id(null(), json!(4)).unwrap();
+        let leaked = handles.insert_with_unique_id(null(), json!(5)).unwrap();
+        handles.promote(nested).unwrap();
+        assert_eq!(handles.end_scope(null(), false).unwrap(), 1);
+        assert!(handles.get_with_id(leaked).is_err());
+        assert!(handles.get_with_id(nested).is_ok());
+
+        handles.promote(promoted).unwrap();
+        assert!(handles.promote(promoted).is_err());
+        assert!(handles.promote(persistent).is_err());
+
+        // Scope left open by plugin is ended together with the automatic one.
+        handles.begin_scope(null(), false);
+        let child = handles.insert_child(null(), persistent, "").unwrap();
+        assert_eq!(handles.end_scope(null(), true).unwrap(), 3);
+        assert!(handles.end_scope(null(), true).is_err());
+
+        assert!(handles.get_with_id(destroyed).is_err());
+        assert!(handles.get_with_id(nested).is_err());
+        assert!(handles.get_with_id(child).is_err());
+        assert!(handles.get_with_id(promoted).is_ok());
+        assert!(handles.get_with_id(persistent).is_ok());
+    }
+
+    #[test]
+    fn scopes_of_different_plugins() {
+        let (first, second) = (0u8, 0u8);
+        let first = &first as *const u8 as *const c_void;
+        let second = &second as *const u8 as *const c_void;
+        let mut handles = JsonHandles::new();
+
+        assert_eq!(handles.begin_scope(first, false), 1);
+        let first_outer = handles.insert_with_unique_id(first, json!(1)).unwrap();
+        assert_eq!(handles.begin_scope(second, true), 1);
+        let second_outer = handles.insert_with_unique_id(second, json!(2)).unwrap();
+        assert_eq!(handles.begin_scope(first, false), 2);
+        assert_eq!(handles.begin_scope(second, false), 2);
+        let second_inner = handles.insert_with_unique_id(second, json!(3)).unwrap();
+        let first_inner = handles.insert_with_unique_id(first, json!(4)).unwrap();
+
+        // Each plugin ends only its own scopes.
+        assert_eq!(handles.end_scope(first, false).unwrap(), 1);
+        assert!(handles.get_with_id(first_inner).is_err());
+        assert!(handles.get_with_id(second_inner).is_ok());
+
+        handles.promote(second_inner).unwrap();
+        assert_eq!(handles.end_scope(second, false).unwrap(), 0);
+        assert!(handles.end_scope(second, false).is_err());
+
+        assert_eq!(handles.end_scope(second, true).unwrap(), 2);
+        assert!(handles.get_with_id(second_outer).is_err());
+        assert!(handles.get_with_id(second_inner).is_err());
+        assert!(handles.get_with_id(first_outer).is_ok());
+
+        assert_eq!(handles.end_scope(first, false).unwrap(), 1);
+        assert!(handles.get_with_id(first_outer).is_err());
+        assert!(handles.end_scope(first, false).is_err());
+    }
+
+    #[test]
+    fn frozen_handles() {
+        let mut handles = JsonHandles::new();
+        let root = handles
+            .insert_with_unique_id(null(), json!({"a": [1]}))
+            .unwrap();
+        let child = handles.insert_child(null(), root, "/a").unwrap();
+
+        assert!(handles.retain(null(), root).is_err());
+        assert!(handles.freeze(child).is_err());
+        assert!(handles.get_mut_with_id(child).is_ok());
+
+        handles.freeze(root).unwrap();
+        assert!(handles.is_frozen(root).unwrap());
+        assert!(handles.is_frozen(child).unwrap());
+        assert!(handles.get_mut_with_id(root).is_err());
+        assert!(handles.get_mut_with_id(child).is_err());
+        assert_eq!(handles.get_with_id(child).unwrap(), &json!([1]));
+
+        let shared = handles.retain(null(), root).unwrap();
+        handles.remove_with_id(root).unwrap();
+        assert_eq!(handles.get_with_id(shared).unwrap(), &json!({"a": [1]}));
+        assert!(handles.get_mut_with_id(shared).is_err());
+    }
+}
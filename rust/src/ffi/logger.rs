@@ -0,0 +1,154 @@
+/*
+ * gRIP
+ * Copyright (c) 2018 Alik Aslanyan <cplusplus256@gmail.com>
+ *
+ *
+ *    This program is free software; you can redistribute it and/or modify it
+ *    under the terms of the GNU General Public License as published by the
+ *    Free Software Foundation; either version 3 of the License, or (at
+ *    your option) any later version.
+ *
+ *    This program is distributed in the hope that it will be useful, but
+ *    WITHOUT ANY WARRANTY; without even the implied warranty of
+ *    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+ *    General Public License for more details.
+ *
+ *    You should have received a copy of the GNU General Public License
+ *    along with this program; if not, write to the Free Software Foundation,
+ *    Inc., 59 Temple Place, Suite 330, Boston, MA  02111-1307  USA
+ *
+ *    In addition, as a special exception, the author gives permission to
+ *    link the code of this program with the Half-Life Game Engine ("HL
+ *    Engine") and Modified Game Libraries ("MODs") developed by Valve,
+ *    L.L.C ("Valve").  You must obey the GNU General Public License in all
+ *    respects for all of the code used other than the HL Engine and MODs
+ *    from Valve.  If you modify this file, you may extend this exception
+ *    to your version of the file, but you are not obligated to do so.  If
+ *    you do not wish to do so, delete this exception statement from your
+ *    version.
+ *
+ */
+
+use lazy_static::*;
+use libc::c_char;
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use std::sync::Mutex;
+use std::thread::{self, ThreadId};
+
+/// AMXX log of the module, which is used from the main thread only.
+struct Target {
+    thread: ThreadId,
+    logger: extern "C" fn(*const c_char),
+}
+
+/// `log` backend. AMXX functions can't be called from the worker threads,
+/// so their records are queued and written on the main thread by `flush`.
+struct AmxxLogger;
+
+static LOGGER: AmxxLogger = AmxxLogger;
+
+/// Records of the worker threads, which are kept until the next `flush`.
+const MAX_QUEUED_RECORDS: usize = 1000;
+
+#[derive(Default)]
+struct Queue {
+    records: Vec<String>,
+    dropped: usize,
+}
+
+lazy_static! {
+    static ref TARGET: Mutex<Option<Target>> = Mutex::new(None);
+    static ref QUEUE: Mutex<Queue> = Mutex::new(Queue::default());
+}
+
+/// Routes records of the current thread to `logger`.
+pub fn init(logger: extern "C" fn(*const c_char), level: LevelFilter) {
+    *TARGET.lock().unwrap() = Some(Target {
+        thread: thread::current().id(),
+        logger,
+    });
+
+    // Logger can be set only once, while module can be initialized again.
+    log::set_logger(&LOGGER).ok();
+    log::set_max_level(level);
+    flush();
+}
+
+pub fn deinit() {
+    *TARGET.lock().unwrap() = None;
+}
+
+/// Writes queued records of the worker threads, when called from the main thread.
+pub fn flush() {
+    if let Some(target) = &*TARGET.lock().unwrap() {
+        if target.thread == thread::current().id() {
+            write_queued(target);
+        }
+    }
+}
+
+fn write(target: &Target, message: &str) {
+    (target.logger)(format!("{}\0", message).as_ptr() as *const c_char)
+}
+
+fn write_queued(target: &Target) {
+    let queue = std::mem::take(&mut *QUEUE.lock().unwrap());
+    for message in &queue.records {
+        write(target, message);
+    }
+
+    if queue.dropped > 0 {
+        write(
+            target,
+            &format!(
+                "[gRIP] Warning: {} log records of the worker threads were dropped",
+                queue.dropped
+            ),
+        );
+    }
+}
+
+fn level_name(level: Level) -> &'static str {
+    match level {
+        Level::Error => "Error",
+        Level::Warn => "Warning",
+        Level::Info => "Info",
+        Level::Debug => "Debug",
+        Level::Trace => "Trace",
+    }
+}
+
+impl Log for AmxxLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let message = format!("[gRIP] {}: {}", level_name(record.level()), record.args());
+        match &*TARGET.lock().unwrap() {
+            Some(target) if target.thread == thread::current().id() => {
+                write_queued(target);
+                write(target, &message);
+            }
+            Some(_) => {
+                let mut queue = QUEUE.lock().unwrap();
+                if queue.records.len() < MAX_QUEUED_RECORDS {
+                    queue.records.push(message);
+                } else {
+                    queue.dropped += 1;
+                }
+            }
+            // Module isn't initialized, so there is no AMXX log to write to and stdout
+            // (server console) is the only option.
+            None => println!("{}", message),
+        }
+    }
+
+    fn flush(&self) {
+        flush();
+    }
+}
@@ -29,13 +29,17 @@
  *
  */
 
-extern crate ini;
-extern crate libc;
+// Natives are called only by the C++ wrappers in `cpp/main.cpp`, which pass the AMX of the
+// calling plugin and pointers to its memory. Their safety contract is the same for every native,
+// so it isn't repeated in the doc comment of each one.
+#![allow(clippy::missing_safety_doc)]
 
-use self::ini::Ini;
+extern crate libc;
 
 #[macro_use]
 mod ext;
+mod json_handle;
+mod logger;
 
 use serde_json::json;
 
@@ -43,33 +47,58 @@ use crate::ffi::ext::*;
 
 use self::libc::{c_char, c_void};
 
+use std::collections::HashMap;
 use std::ffi::CStr;
 use std::fs::File;
 use std::io::BufReader;
 
+use crate::config::Config;
 use crate::errors::*;
 use lazy_static::*;
 
 type Cell = isize;
 
 use crate::networking_queue::{
-    Queue, RequestBuilder, RequestCancellation, RequestOptions, RequestType, Response,
+    ExecutionStats, Queue, RequestBuilder, RequestCancellation, RequestOptions, RequestPriority,
+    RequestType, Response, ResponseInfo, RetryConditions, RetryPolicy,
 };
 use std::prelude::v1::Vec;
 
-use crate::cell_map::CellMap;
+use self::json_handle::JsonHandles;
+use crate::cell_map::{CellMap, HandleInfo, HandleKind};
 use serde_json::Value;
 
+/// Request body with the content type, which is used unless options override it.
+#[derive(Constructor, Default)]
+struct RequestBody {
+    pub data: Vec<u8>,
+    pub content_type: Option<reqwest::header::HeaderValue>,
+}
+
+/// Callbacks execution of the last frame, which executed them.
+#[derive(Clone, Copy, Default)]
+struct FrameStats {
+    pub execution: ExecutionStats,
+    pub pending_requests: usize,
+    pub waiting_requests: usize,
+    pub in_flight_requests: usize,
+    pub multiplier: usize,
+}
+
 struct ModuleStorage {
     pub global_queue: Queue,
     pub current_response: Option<Result<Response>>,
-    pub bodies_handles: CellMap<Vec<u8>>,
+    pub current_response_info: ResponseInfo,
+    pub bodies_handles: CellMap<RequestBody>,
     pub cancellations_handles: CellMap<RequestCancellation>,
-    pub json_handles: CellMap<Value>,
+    pub json_handles: JsonHandles,
     pub options_handles: CellMap<RequestOptions>,
     pub error_logger: extern "C" fn(*const c_void, *const c_char),
-    pub callbacks_per_frame: usize,
-    pub microseconds_delay_between_attempts: usize,
+    pub logger: extern "C" fn(*const c_char),
+    pub plugin_name_getter: extern "C" fn(*const c_void) -> *const c_char,
+    pub config: Config,
+    pub config_file_path: String,
+    pub frame_stats: FrameStats,
 }
 
 static mut MODULE: Option<ModuleStorage> = None;
@@ -77,103 +106,339 @@ static mut MODULE: Option<ModuleStorage> = None;
 #[no_mangle]
 pub unsafe extern "C" fn grip_init(
     error_logger: extern "C" fn(*const c_void, *const c_char),
+    logger: extern "C" fn(*const c_char),
+    plugin_name_getter: extern "C" fn(*const c_void) -> *const c_char,
     config_file_path: *const c_char,
 ) {
-    if MODULE.is_some() {
-        return;
+    catch_panic_ffi!(std::ptr::null(), (), {
+        if try_get_module().is_some() {
+            return;
+        }
+
+        let config_file_path = CStr::from_ptr(config_file_path)
+            .to_string_lossy()
+            .into_owned();
+        let (config, _) = load_config(logger, &config_file_path);
+
+        MODULE = Some(ModuleStorage {
+            global_queue: Queue::new(),
+            cancellations_handles: CellMap::new(HandleKind::Cancellation),
+            current_response: None,
+            current_response_info: ResponseInfo::default(),
+            bodies_handles: CellMap::new(HandleKind::Body),
+            json_handles: JsonHandles::new(),
+            options_handles: CellMap::new(HandleKind::Options),
+            error_logger,
+            logger,
+            plugin_name_getter,
+            config,
+            config_file_path,
+            frame_stats: FrameStats::default(),
+        });
+
+        logger::init(logger, get_module().config.log_level);
+        apply_config();
+    })
+}
+
+/// Loads config and reports problems to the AMXX log. Returns config and the same report.
+fn load_config(logger: extern "C" fn(*const c_char), path: &str) -> (Config, String) {
+    let (config, messages) = Config::load_from_file(path);
+
+    let mut report = String::new();
+    for message in messages {
+        let message = format!("[gRIP] {}: {}", path, message);
+        logger(format!("{}\0", message).as_ptr() as *const c_char);
+        report += &message;
+        report += "\n";
     }
 
-    let ini = Ini::load_from_file(str_from_ptr(config_file_path).unwrap())
-        .map_err(|e| {
-            println!(
-                "Error: Can't parse/open grip config. Examine carefully ini parser log message\n{}",
-                e
-            );
-            e
-        })
-        .unwrap();
-
-    let queue_section = ini
-        .section(Some("queue".to_owned()))
-        .or_else(|| {
-            println!("Error: Missing [queue] section in the grip.ini config");
-            None
-        })
-        .unwrap();
-
-    MODULE = Some(ModuleStorage {
-        global_queue: Queue::new(),
-        cancellations_handles: CellMap::new(),
-        current_response: None,
-        bodies_handles: CellMap::new(),
-        json_handles: CellMap::new(),
-        options_handles: CellMap::new(),
-        error_logger,
-        callbacks_per_frame: {
-            queue_section
-                .get("callbacks-per-frame")
-                .or_else(|| {
-                    println!(
-                        "Error: Missing \"queue.callbacks-per-frame\" key in the grip.ini config"
-                    );
-                    None
-                })
-                .unwrap()
-                .parse()
-                .unwrap()
-        },
-        microseconds_delay_between_attempts: {
-            queue_section
-                .get("microseconds-delay-between-attempts")
-                .or_else(|| {
-                    println!("Error: Missing \"queue.microseconds-delay-between-attempts\" key in the grip.ini config");
-                    None
-                }).unwrap()
-                .parse()
-                .unwrap()
-        },
-    });
+    (config, report)
+}
+
+/// Passes settings, which are stored outside of the module config.
+unsafe fn apply_config() {
+    let module = get_module_mut();
+    log::set_max_level(module.config.log_level);
+    module
+        .global_queue
+        .set_max_rate_limit_hold(std::time::Duration::from_secs(
+            module.config.max_rate_limit_hold_seconds as u64,
+        ));
+    module
+        .global_queue
+        .set_rate_limit_groups(&module.config.rate_limit_groups);
+    module.global_queue.set_concurrency_limits(
+        module.config.max_concurrent_requests,
+        module.config.max_concurrent_requests_per_host,
+    );
+}
+
+/// Module is accessed through the raw pointer, so no reference to the mutable static itself is made.
+unsafe fn try_get_module() -> Option<&'static ModuleStorage> {
+    (*std::ptr::addr_of!(MODULE)).as_ref()
+}
+
+unsafe fn try_get_module_mut() -> Option<&'static mut ModuleStorage> {
+    (*std::ptr::addr_of_mut!(MODULE)).as_mut()
 }
 
 unsafe fn get_module() -> &'static ModuleStorage {
-    MODULE.as_ref().unwrap()
+    try_get_module().expect("gRIP module is not initialized")
 }
 
 unsafe fn get_module_mut() -> &'static mut ModuleStorage {
-    MODULE.as_mut().unwrap()
+    try_get_module_mut().expect("gRIP module is not initialized")
+}
+
+/// Reports panic caught at the FFI boundary. AMX could be absent for the module callbacks.
+unsafe fn log_panic(amx: *const c_void, message: &str) {
+    let message = format!("Internal gRIP error, please report it: {}", message);
+    match try_get_module() {
+        Some(module) if !amx.is_null() => {
+            (module.error_logger)(amx, format!("{}\0", message).as_ptr() as *const c_char)
+        }
+        Some(module) => {
+            (module.logger)(format!("[gRIP] Error: {}\0", message).as_ptr() as *const c_char)
+        }
+        // Panic happened before `grip_init` received the AMXX logger, or after `grip_deinit`.
+        None => println!("[gRIP] Error: {}", message),
+    }
 }
 
 #[no_mangle]
 pub unsafe extern "C" fn grip_deinit() {
-    if MODULE.is_some() {
-        get_module_mut().cancellations_handles.clear(); // Cancel all operations, before queue stopped.
+    catch_panic_ffi!(std::ptr::null(), (), {
+        if try_get_module().is_some() {
+            get_module_mut().cancellations_handles.clear(); // Cancel all operations, before queue stopped.
+        }
+        MODULE = None;
+        logger::deinit();
+    })
+}
+
+lazy_static! {
+    /// Number of handles of every plugin and kind, which were left at the previous map change.
+    /// Module is recreated for every map, so it's kept outside of it.
+    static ref LEFT_HANDLES: std::sync::Mutex<HashMap<(String, &'static str), usize>> =
+        std::sync::Mutex::new(HashMap::new());
+}
+
+/// Frees handles of every plugin, while their AMX'es are still valid. Paused plugins are
+/// unloaded as well, so their handles are freed and counted the same way.
+/// Plugin can keep handles for its whole lifetime, so left handles are reported as a possible
+/// leak only when their number grows between map changes.
+#[no_mangle]
+pub unsafe extern "C" fn grip_plugins_unloading() {
+    catch_panic_ffi!(std::ptr::null(), (), {
+        if try_get_module().is_none() {
+            return;
+        }
+
+        let mut left = HashMap::new();
+        let mut owners = std::collections::BTreeSet::new();
+        for (owner, _, kind, _) in live_handles() {
+            *left
+                .entry((plugin_name(owner as *const c_void), kind))
+                .or_insert(0) += 1;
+            owners.insert(owner);
+        }
+
+        for owner in owners {
+            destroy_owned_handles(owner as *const c_void);
+        }
+
+        let mut previous = LEFT_HANDLES.lock().unwrap();
+        let mut sorted: Vec<_> = left.iter().collect();
+        sorted.sort();
+        for ((plugin, kind), &count) in sorted {
+            let before = previous.get(&(plugin.clone(), *kind)).cloned().unwrap_or(0);
+            if count > before {
+                warn!(
+                    "Plugin \"{}\" left {} live {} handle(s), {} at the previous map change. Possible leak",
+                    plugin, count, kind, before
+                );
+            }
+        }
+        *previous = left;
+    })
+}
+
+unsafe fn plugin_name(amx: *const c_void) -> String {
+    ptr_to_option((get_module().plugin_name_getter)(amx))
+        .map(|name| CStr::from_ptr(name).to_string_lossy().into_owned())
+        .unwrap_or_else(|| format!("{:?}", amx))
+}
+
+/// Returns owner, id, handle type and age of every live handle.
+unsafe fn live_handles() -> Vec<(usize, Cell, &'static str, std::time::Duration)> {
+    fn collect<'a>(
+        handles: impl Iterator<Item = (Cell, &'a HandleInfo)> + 'a,
+        kind: &'static str,
+    ) -> impl Iterator<Item = (usize, Cell, &'static str, std::time::Duration)> + 'a {
+        handles.map(move |(id, info)| (info.owner as usize, id, kind, info.created.elapsed()))
     }
-    MODULE = None;
+
+    let module = get_module();
+    let mut handles: Vec<_> = collect(module.bodies_handles.infos(), "GripBody")
+        .chain(collect(
+            module.cancellations_handles.infos(),
+            "GripRequestCancellation",
+        ))
+        .chain(collect(module.json_handles.infos(), "GripJSONValue"))
+        .chain(collect(
+            module.options_handles.infos(),
+            "GripRequestOptions",
+        ))
+        .collect();
+
+    handles.sort_by_key(|(owner, id, kind, _)| (*owner, *kind, *id));
+    handles
+}
+
+unsafe fn destroy_owned_handles(amx: *const c_void) -> usize {
+    let module = get_module_mut();
+
+    module.bodies_handles.remove_owned_by(amx)
+        + module.cancellations_handles.remove_owned_by(amx)
+        + module.json_handles.remove_owned_by(amx)
+        + module.options_handles.remove_owned_by(amx)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn grip_handles_report(
+    amx: *const c_void,
+    buffer: *mut c_char,
+    maxlen: Cell,
+) -> Cell {
+    catch_panic_ffi!(amx, {
+        if try_get_module().is_none() {
+            return 0;
+        }
+
+        let handles = live_handles();
+        let mut report = format!("[gRIP] Live handles: {}\n", handles.len());
+
+        let mut current_owner = None;
+        for (owner, id, kind, age) in handles {
+            if current_owner != Some(owner) {
+                current_owner = Some(owner);
+                report += &format!("{}:\n", plugin_name(owner as *const c_void));
+            }
+
+            report += &format!(
+                "    #{} {}, age {:.1}s\n",
+                id,
+                kind,
+                age.as_secs() as f64 + f64::from(age.subsec_millis()) / 1000.0
+            );
+        }
+
+        copy_unsafe_string!(amx, buffer, report, maxlen)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn grip_destroy_owned_handles(amx: *const c_void) -> Cell {
+    catch_panic_ffi!(amx, { destroy_owned_handles(amx) as Cell })
+}
+
+/// Re-reads config and applies new settings to the running module and queue.
+#[no_mangle]
+pub unsafe extern "C" fn grip_reload_config(
+    amx: *const c_void,
+    buffer: *mut c_char,
+    maxlen: Cell,
+) -> Cell {
+    catch_panic_ffi!(amx, {
+        if try_get_module().is_none() {
+            return 0;
+        }
+
+        let module = get_module_mut();
+        let (config, mut report) = load_config(module.logger, &module.config_file_path);
+        let changes = module.config.reload(&config);
+        apply_config();
+
+        report += &format!(
+            "[gRIP] Reloaded {}, changed settings: {}\n",
+            module.config_file_path,
+            changes.len()
+        );
+        for change in &changes {
+            report += &format!("    {}\n", change);
+        }
+
+        // Native returns number of changed settings, not length of the report.
+        let _ = copy_unsafe_string!(amx, buffer, report, maxlen);
+
+        changes.len() as Cell
+    })
 }
 
 #[no_mangle]
 pub unsafe extern "C" fn grip_destroy_body(amx: *const c_void, body: Cell) -> Cell {
-    try_and_log_ffi!(
-        amx,
-        get_module_mut()
-            .bodies_handles
-            .remove_with_id(body)
-            .chain_err(|| ffi_error(format!("Invalid body handle {}", body)))
-    );
+    catch_panic_ffi!(amx, {
+        try_and_log_ffi!(
+            amx,
+            get_module_mut()
+                .bodies_handles
+                .remove_with_id(body)
+                .chain_err(|| ffi_error(format!("Invalid body handle {}", body)))
+        );
 
-    1
+        1
+    })
 }
 
 #[no_mangle]
 pub unsafe extern "C" fn grip_body_from_string(amx: *const c_void, str: *const c_char) -> Cell {
-    get_module_mut().bodies_handles.insert_with_unique_id(
-        CStr::from_ptr(try_and_log_ffi!(
+    catch_panic_ffi!(amx, {
+        try_and_log_ffi!(
             amx,
-            ptr_to_option(str).chain_err(|| ffi_error("Invalid URI."))
-        ))
-        .to_bytes()
-        .to_vec(),
-    )
+            get_module_mut().bodies_handles.insert_with_unique_id(
+                amx,
+                RequestBody::new(
+                    CStr::from_ptr(try_and_log_ffi!(
+                        amx,
+                        ptr_to_option(str).chain_err(|| ffi_error("Invalid URI."))
+                    ))
+                    .to_bytes()
+                    .to_vec(),
+                    None,
+                ),
+            )
+        )
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn grip_body_from_json(
+    amx: *const c_void,
+    value: Cell,
+    pretty: bool,
+) -> Cell {
+    catch_panic_ffi!(amx, {
+        let data = try_and_log_ffi!(
+            amx,
+            serialize_json(try_to_get_json_value!(amx, value), pretty)
+                .chain_err(|| ffi_error(format!("Can't serialize JSON value {}", value)))
+        )
+        .into_bytes();
+
+        try_and_log_ffi!(
+            amx,
+            get_module_mut().bodies_handles.insert_with_unique_id(
+                amx,
+                RequestBody::new(
+                    data,
+                    Some(reqwest::header::HeaderValue::from_static(
+                        "application/json",
+                    )),
+                ),
+            )
+        )
+    })
 }
 
 #[no_mangle]
@@ -187,17 +452,138 @@ pub unsafe extern "C" fn grip_request(
     options_handle: Cell,
     user_data: Cell,
 ) -> Cell {
-    let request_type = try_and_log_ffi!(
-        amx,
-        match request_type {
-            0 => Ok(RequestType::Get),
-            1 => Ok(RequestType::Post),
-            2 => Ok(RequestType::Put),
-            3 => Ok(RequestType::Delete),
-            _ => Err(ErrorKind::FFIError(format!("Invalid request type {}", request_type)).into()),
+    catch_panic_ffi!(amx, {
+        let request_type = try_and_log_ffi!(
+            amx,
+            match request_type {
+                0 => Ok(RequestType::Get),
+                1 => Ok(RequestType::Post),
+                2 => Ok(RequestType::Put),
+                3 => Ok(RequestType::Delete),
+                4 => Ok(RequestType::Patch),
+                5 => Ok(RequestType::Head),
+                6 => Ok(RequestType::Options),
+                _ => Err(
+                    ErrorKind::FFIError(format!("Invalid request type {}", request_type)).into()
+                ),
+            }
+        );
+
+        start_request(
+            amx,
+            forward_id,
+            uri,
+            body_handle,
+            request_type,
+            handler,
+            options_handle,
+            user_data,
+        )
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn grip_request_custom_method(
+    amx: *const c_void,
+    forward_id: Cell,
+    uri: *const c_char,
+    body_handle: Cell,
+    method: *const c_char,
+    handler: Option<extern "C" fn(forward_handle: Cell, user_data: Cell) -> c_void>,
+    options_handle: Cell,
+    user_data: Cell,
+) -> Cell {
+    catch_panic_ffi!(amx, {
+        let request_type = RequestType::Custom(
+            try_and_log_ffi!(
+                amx,
+                str_from_ptr(method)
+                    .chain_err(|| ffi_error("Invalid HTTP method. Can't create UTF-8 string"))
+            )
+            .to_owned(),
+        );
+
+        // Validate verb here, so error is reported to the plugin instead of the callback.
+        try_and_log_ffi!(amx, request_type.to_method());
+
+        start_request(
+            amx,
+            forward_id,
+            uri,
+            body_handle,
+            request_type,
+            handler,
+            options_handle,
+            user_data,
+        )
+    })
+}
+
+/// Restores the module state after the request handler, even if it panics.
+struct ResponseHandlerGuard<'a> {
+    amx: *const c_void,
+    plugin: &'a str,
+    cancellation_id: Cell,
+}
+
+impl Drop for ResponseHandlerGuard<'_> {
+    fn drop(&mut self) {
+        let module = match unsafe { try_get_module_mut() } {
+            Some(module) => module,
+            None => return,
+        };
+
+        if let Err(e) = module.json_handles.end_scope(self.amx, true) {
+            error!(
+                "Plugin \"{}\": Can't close JSON scope of the request handler: {}",
+                self.plugin, e
+            );
         }
-    );
 
+        // Handle could be already destroyed by the plugin.
+        module
+            .cancellations_handles
+            .remove_with_id(self.cancellation_id)
+            .ok();
+
+        module.current_response = None;
+    }
+}
+
+/// Makes the response available to the natives for the time of the `handler` call.
+/// JSON handles created by the handler are destroyed, when it returns.
+unsafe fn call_response_handler(
+    amx: *const c_void,
+    plugin: &str,
+    cancellation_id: Cell,
+    response: Result<Response>,
+    info: ResponseInfo,
+    handler: impl FnOnce(),
+) {
+    let module = get_module_mut();
+    module.current_response = Some(response);
+    module.current_response_info = info;
+    module.json_handles.begin_scope(amx, true);
+
+    let _guard = ResponseHandlerGuard {
+        amx,
+        plugin,
+        cancellation_id,
+    };
+    handler();
+}
+
+#[allow(clippy::too_many_arguments)]
+unsafe fn start_request(
+    amx: *const c_void,
+    forward_id: Cell,
+    uri: *const c_char,
+    body_handle: Cell,
+    request_type: RequestType,
+    handler: Option<extern "C" fn(forward_handle: Cell, user_data: Cell) -> c_void>,
+    options_handle: Cell,
+    user_data: Cell,
+) -> Cell {
     let uri = try_and_log_ffi!(
         amx,
         CStr::from_ptr(try_and_log_ffi!(
@@ -213,13 +599,13 @@ pub unsafe extern "C" fn grip_request(
         get_module()
             .bodies_handles
             .get_with_id(body_handle)
-            .or_else(|| if body_handle == -1 {
+            .or_else(|e| if body_handle == -1 {
                 lazy_static! {
-                    static ref EMPTY_VEC: Vec<u8> = vec![];
+                    static ref EMPTY_BODY: RequestBody = RequestBody::default();
                 }
-                Some(&EMPTY_VEC)
+                Ok(&EMPTY_BODY)
             } else {
-                None
+                Err(e)
             })
             .chain_err(|| ffi_error(format!("Invalid body handle: {}", body_handle)))
     );
@@ -229,96 +615,170 @@ pub unsafe extern "C" fn grip_request(
         get_module()
             .options_handles
             .get_with_id(options_handle)
-            .or_else(|| if options_handle == -1 {
+            .or_else(|e| if options_handle == -1 {
                 lazy_static! {
                     static ref EMPTY_OPTIONS: RequestOptions = RequestOptions::default();
                 }
-                Some(&EMPTY_OPTIONS)
+                Ok(&EMPTY_OPTIONS)
             } else {
-                None
+                Err(e)
             })
             .chain_err(|| ffi_error(format!("Invalid options handle: {}", options_handle)))
     );
 
-    // TODO: JSON
+    let mut options = options.clone();
+    if let Some(content_type) = &body.content_type {
+        if !options.headers.contains_key(reqwest::header::CONTENT_TYPE) {
+            options
+                .headers
+                .insert(reqwest::header::CONTENT_TYPE, content_type.clone());
+        }
+    }
+
+    let handler = try_and_log_ffi!(
+        amx,
+        handler.chain_err(|| ffi_error("Invalid response handler."))
+    );
+
+    // Responses are grouped by plugin, so one plugin can't starve others.
+    let plugin = plugin_name(amx);
+    let weight = get_module().config.plugin_weight(&plugin);
+    get_module_mut()
+        .global_queue
+        .set_group_weight(amx as usize, weight);
 
-    let next_cancellation_id = get_module().cancellations_handles.peek_id();
+    let next_cancellation_id = try_and_log_ffi!(amx, get_module().cancellations_handles.peek_id());
+    // Handler is called on the main thread, but the pointer itself isn't `Send`.
+    let owner = amx as usize;
     let cancellation = get_module_mut().global_queue.send_request(
         RequestBuilder::default()
             .http_type(request_type)
-            .body(body.clone())
+            .body(body.data.clone())
             .uri(try_and_log_ffi!(
                 amx,
                 uri.parse()
                     .chain_err(|| ffi_error(format!("URI parsing error: {}", uri)))
             ))
-            .options(options.clone())
+            .options(options)
+            .group(amx as usize)
             .build()
             .unwrap(),
-        move |response| {
-            get_module_mut().current_response = Some(response);
-
-            handler.unwrap()(forward_id, user_data);
-
-            get_module_mut()
-                .cancellations_handles
-                .remove_with_id(next_cancellation_id);
-
-            get_module_mut().current_response = None;
+        move |response, info| {
+            call_response_handler(
+                owner as *const c_void,
+                &plugin,
+                next_cancellation_id,
+                response,
+                info,
+                || {
+                    handler(forward_id, user_data);
+                },
+            )
         },
     );
 
-    get_module_mut()
-        .cancellations_handles
-        .insert_with_unique_id(cancellation)
+    try_and_log_ffi!(
+        amx,
+        get_module_mut()
+            .cancellations_handles
+            .insert_with_unique_id(amx, cancellation)
+    )
 }
 
 //cell grip_cancel_request(const void* amx, cell cancellation);
 #[no_mangle]
 pub unsafe extern "C" fn grip_cancel_request(amx: *const c_void, cancellation: Cell) -> Cell {
-    try_and_log_ffi!(
-        amx,
-        get_module_mut()
-            .cancellations_handles
-            .remove_with_id(cancellation)
-            .chain_err(|| ffi_error(format!(
-                "Cancellation with the id {} doesn't exist.",
-                cancellation
-            )))
-    );
+    catch_panic_ffi!(amx, {
+        try_and_log_ffi!(
+            amx,
+            get_module_mut()
+                .cancellations_handles
+                .remove_with_id(cancellation)
+                .chain_err(|| ffi_error(format!(
+                    "Cancellation with the id {} doesn't exist.",
+                    cancellation
+                )))
+        );
 
-    1
+        1
+    })
 }
 
 #[no_mangle]
 pub unsafe extern "C" fn grip_get_response_state(amx: *const c_void) -> Cell {
-    match try_and_log_ffi!(
-        amx,
-        get_module()
-            .current_response
-            .as_ref()
-            .chain_err(|| ffi_error("Response state can only be received in the request callback"))
-    ) {
-        Err(e) => match e.kind() {
-            crate::errors::ErrorKind::RequestCancelled => 1,
-            crate::errors::ErrorKind::RequestTimeout => 4,
-            _ => 2,
-        },
-        Ok(_) => 3,
-    }
+    catch_panic_ffi!(amx, {
+        match try_and_log_ffi!(
+            amx,
+            get_module()
+                .current_response
+                .as_ref()
+                .chain_err(|| ffi_error(
+                    "Response state can only be received in the request callback"
+                ))
+        ) {
+            Err(e) => match e.kind() {
+                crate::errors::ErrorKind::RequestCancelled => 1,
+                crate::errors::ErrorKind::RequestTimeout => 4,
+                crate::errors::ErrorKind::RequestRateLimited => 5,
+                _ => 2,
+            },
+            Ok(_) => 3,
+        }
+    })
 }
 
 #[no_mangle]
-pub unsafe extern "C" fn grip_is_request_active(request_id: Cell) -> Cell {
-    if get_module()
-        .cancellations_handles
-        .get_with_id(request_id)
-        .is_some()
-    {
+pub unsafe extern "C" fn grip_get_response_attempts(amx: *const c_void) -> Cell {
+    catch_panic_ffi!(amx, {
+        try_and_log_ffi!(
+            amx,
+            get_module()
+                .current_response
+                .as_ref()
+                .chain_err(|| ffi_error(
+                    "Response attempts can only be received in the request callback"
+                ))
+        );
+
+        get_module().current_response_info.attempts as Cell
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn grip_get_response_held_time(amx: *const c_void, ret: *mut f32) -> Cell {
+    catch_panic_ffi!(amx, {
+        *ret = 0.0;
+
+        try_and_log_ffi!(
+            amx,
+            get_module()
+                .current_response
+                .as_ref()
+                .chain_err(|| ffi_error(
+                    "Response held time can only be received in the request callback"
+                ))
+        );
+
+        let held = get_module().current_response_info.held;
+        *ret = held.as_secs() as f32 + held.subsec_micros() as f32 / 1_000_000.0;
+
         1
-    } else {
-        0
-    }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn grip_is_request_active(request_id: Cell) -> Cell {
+    catch_panic_ffi!(std::ptr::null(), {
+        if get_module()
+            .cancellations_handles
+            .get_with_id(request_id)
+            .is_ok()
+        {
+            1
+        } else {
+            0
+        }
+    })
 }
 
 #[no_mangle]
@@ -327,26 +787,28 @@ pub unsafe extern "C" fn grip_get_error_description(
     buffer: *mut c_char,
     size: Cell,
 ) -> Cell {
-    if let Err(e) = try_and_log_ffi!(
-        amx,
-        get_module()
-            .current_response
-            .as_ref()
-            .chain_err(|| ffi_error("No active response at this time"))
-    ) {
-        try_and_log_ffi!(
+    catch_panic_ffi!(amx, {
+        if let Err(e) = try_and_log_ffi!(
             amx,
-            match e.kind() {
-                ErrorKind::RequestCancelled => Err(ErrorKind::RequestCancelled.into()),
-                _ => Ok(()),
-            }
-        );
+            get_module()
+                .current_response
+                .as_ref()
+                .chain_err(|| ffi_error("No active response at this time"))
+        ) {
+            try_and_log_ffi!(
+                amx,
+                match e.kind() {
+                    ErrorKind::RequestCancelled => Err(ErrorKind::RequestCancelled.into()),
+                    _ => Ok(()),
+                }
+            );
 
-        use error_chain::ChainedError;
-        copy_unsafe_string!(amx, buffer, e.display_chain(), size)
-    } else {
-        try_and_log_ffi!(amx, Err(ffi_error("No error for this response.")))
-    }
+            use error_chain::ChainedError;
+            copy_unsafe_string!(amx, buffer, e.display_chain(), size)
+        } else {
+            try_and_log_ffi!(amx, Err(ffi_error("No error for this response.")))
+        }
+    })
 }
 
 #[no_mangle]
@@ -355,452 +817,1946 @@ pub unsafe extern "C" fn grip_get_response_body_string(
     buffer: *mut c_char,
     size: Cell,
 ) -> Cell {
-    if let Ok(response) = try_and_log_ffi!(
-        amx,
-        get_module()
-            .current_response
-            .as_ref()
-            .chain_err(|| ffi_error("No active response at this time"))
-    ) {
-        copy_unsafe_string!(
+    catch_panic_ffi!(amx, {
+        if let Ok(response) = try_and_log_ffi!(
             amx,
-            buffer,
-            try_and_log_ffi!(
+            get_module()
+                .current_response
+                .as_ref()
+                .chain_err(|| ffi_error("No active response at this time"))
+        ) {
+            copy_unsafe_string!(
                 amx,
-                std::str::from_utf8(&response.body[..])
-                    .chain_err(|| ffi_error("Unable to parse UTF-8"))
-            ),
-            size
-        )
-    } else {
-        unconditionally_log_error!(
-            amx,
-            ffi_error("Error/Cancellation/Timeout occurred for this response.")
-        )
-    }
+                buffer,
+                try_and_log_ffi!(
+                    amx,
+                    std::str::from_utf8(&response.body[..])
+                        .chain_err(|| ffi_error("Unable to parse UTF-8"))
+                ),
+                size
+            )
+        } else {
+            unconditionally_log_error!(
+                amx,
+                ffi_error("Error/Cancellation/Timeout occurred for this response.")
+            )
+        }
+    })
 }
 
 #[no_mangle]
 pub unsafe extern "C" fn grip_get_response_status_code(amx: *const c_void) -> Cell {
-    if let Ok(response) = try_and_log_ffi!(
-        amx,
-        get_module()
-            .current_response
-            .as_ref()
-            .chain_err(|| ffi_error("No active response at this time"))
-    ) {
-        response.status_code.as_u16() as Cell
-    } else {
-        unconditionally_log_error!(
-            amx,
-            ffi_error("Error/Cancellation/Timeout occurred for this response.")
-        );
-        -1
-    }
-}
-
-#[no_mangle]
-pub unsafe extern "C" fn grip_destroy_json_value(amx: *const c_void, json_value: Cell) -> Cell {
-    try_and_log_ffi!(
-        amx,
-        get_module_mut()
-            .json_handles
-            .remove_with_id(json_value)
-            .chain_err(|| ffi_error(format!("Invalid json value handle {}", json_value)))
-    );
-
-    1
-}
-
-#[no_mangle]
-pub unsafe extern "C" fn grip_create_default_options(amx: *const c_void, timeout: f64) -> Cell {
-    use float_cmp::ApproxEq;
-
-    get_module_mut()
-        .options_handles
-        .insert_with_unique_id(RequestOptions::new(
-            reqwest::header::HeaderMap::default(),
-            try_and_log_ffi!(
+    catch_panic_ffi!(amx, {
+        if let Ok(response) = try_and_log_ffi!(
+            amx,
+            get_module()
+                .current_response
+                .as_ref()
+                .chain_err(|| ffi_error("No active response at this time"))
+        ) {
+            response.status_code.as_u16() as Cell
+        } else {
+            unconditionally_log_error!(
                 amx,
-                if timeout.approx_eq(&-1.0, std::f64::EPSILON, 2) {
-                    Ok(None)
-                } else if timeout >= 0.0 {
-                    Ok(Some(std::time::Duration::from_millis(
-                        (timeout * 1000.0) as u64,
-                    )))
-                } else {
-                    Err(ffi_error(format!("Invalid timeout: {}", timeout)))
-                }
-            ),
-        ))
+                ffi_error("Error/Cancellation/Timeout occurred for this response.")
+            );
+            -1
+        }
+    })
 }
 
 #[no_mangle]
-pub unsafe extern "C" fn grip_destroy_options(amx: *const c_void, options_handle: Cell) -> Cell {
-    try_and_log_ffi!(
-        amx,
-        get_module_mut()
-            .options_handles
-            .remove_with_id(options_handle)
-            .chain_err(|| ffi_error(format!("Invalid options handle {}", options_handle)))
-    );
-
-    1
+pub unsafe extern "C" fn grip_get_response_headers_count(amx: *const c_void) -> Cell {
+    catch_panic_ffi!(amx, { try_to_get_response!(amx).headers.len() as Cell })
 }
 
 #[no_mangle]
-pub unsafe extern "C" fn grip_options_add_header(
+pub unsafe extern "C" fn grip_get_response_header_name_at(
     amx: *const c_void,
-    options_handle: Cell,
-    header_name: *const c_char,
-    header_value: *const c_char,
+    index: Cell,
+    buffer: *mut c_char,
+    size: Cell,
 ) -> Cell {
-    let option = try_and_log_ffi!(
-        amx,
-        get_module_mut()
-            .options_handles
-            .get_mut_with_id(options_handle)
-            .chain_err(|| ffi_error(format!("Invalid options handle: {}", options_handle)))
-    );
-
-    let header_name = try_and_log_ffi!(
-        amx,
-        CStr::from_ptr(header_name)
-            .to_str()
-            .chain_err(|| ffi_error("Invalid header name. Can't create UTF-8 string"))
-    );
-
-    let header_value = try_and_log_ffi!(
-        amx,
-        str_from_ptr(header_value)
-            .chain_err(|| ffi_error("Invalid header value. Can't create UTF-8 string"))
-    );
-
-    let header_value = try_and_log_ffi!(
-        amx,
-        reqwest::header::HeaderValue::from_str(header_value)
-        .chain_err(|| ffi_error(format!("Header value contains invalid byte sequences or was rejected by Hyper HTTP implementation: {}", header_value)))
-    );
-
-    option.headers.insert(header_name, header_value);
+    catch_panic_ffi!(amx, {
+        let (name, _) = try_and_log_ffi!(
+            amx,
+            try_to_get_response!(amx)
+                .headers
+                .iter()
+                .nth(try_as_usize!(amx, index))
+                .chain_err(|| ffi_error(format!("Header index {} is out of bounds", index)))
+        );
 
-    1
+        copy_unsafe_string!(amx, buffer, name.as_str(), size)
+    })
 }
 
 #[no_mangle]
-pub unsafe extern "C" fn grip_process_request() {
-    let multiplier = std::cmp::min(
-        get_module().global_queue.number_of_pending_requests() / 500,
-        1,
-    );
-    if multiplier > 1 {
-        println!("[gRIP] Warning: More than 500 requests are pending.. Fastening execution {} times to compensate that", multiplier);
-    }
+pub unsafe extern "C" fn grip_get_response_header_value_at(
+    amx: *const c_void,
+    index: Cell,
+    buffer: *mut c_char,
+    size: Cell,
+) -> Cell {
+    catch_panic_ffi!(amx, {
+        let (_, value) = try_and_log_ffi!(
+            amx,
+            try_to_get_response!(amx)
+                .headers
+                .iter()
+                .nth(try_as_usize!(amx, index))
+                .chain_err(|| ffi_error(format!("Header index {} is out of bounds", index)))
+        );
 
-    get_module_mut().global_queue.execute_queue_with_limit(
-        get_module().callbacks_per_frame * multiplier,
-        std::time::Duration::from_micros(get_module().microseconds_delay_between_attempts as u64),
-    );
+        copy_unsafe_string!(amx, buffer, String::from_utf8_lossy(value.as_bytes()), size)
+    })
 }
 
 #[no_mangle]
-pub unsafe extern "C" fn grip_json_parse_response_body(
+pub unsafe extern "C" fn grip_get_response_header(
     amx: *const c_void,
-    error_buffer: *mut c_char,
-    error_buffer_size: Cell,
+    name: *const c_char,
+    buffer: *mut c_char,
+    size: Cell,
+    value_index: Cell,
 ) -> Cell {
-    if let Ok(response) = try_and_log_ffi!(
-        amx,
-        get_module()
-            .current_response
-            .as_ref()
-            .chain_err(|| ffi_error("No active response at this time"))
-    ) {
-        let value: Result<serde_json::Value> =
-            serde_json::from_slice(&response.body[..]).map_err(|e| ErrorKind::JSONError(e).into());
+    catch_panic_ffi!(amx, {
+        let name = try_and_log_ffi!(
+            amx,
+            str_from_ptr(name)
+                .chain_err(|| ffi_error("Invalid header name. Can't create UTF-8 string"))
+        );
 
-        match value {
-            Ok(value) => get_module_mut().json_handles.insert_with_unique_id(value),
-            Err(error) => {
-                use error_chain::ChainedError;
-                copy_unsafe_string!(amx, error_buffer, error.display_chain(), error_buffer_size);
-                0
-            }
-        }
-    } else {
-        unconditionally_log_error!(
+        let value = try_and_log_ffi!(
             amx,
-            ffi_error("Error/Cancellation/Timeout occurred for this response.")
-        )
-    }
+            try_to_get_response!(amx)
+                .headers
+                .get_all(name)
+                .iter()
+                .nth(try_as_usize!(amx, value_index))
+                .chain_err(|| ffi_error(format!(
+                    "Response doesn't contain value {} of the header `{}`",
+                    value_index, name
+                )))
+        );
+
+        copy_unsafe_string!(amx, buffer, String::from_utf8_lossy(value.as_bytes()), size)
+    })
 }
 
-// TODO: Remove copy-paste
 #[no_mangle]
-pub unsafe extern "C" fn grip_json_parse_string(
+pub unsafe extern "C" fn grip_get_response_header_values_count(
     amx: *const c_void,
-    string: *mut c_char,
-    error_buffer: *mut c_char,
-    error_buffer_size: Cell,
+    name: *const c_char,
 ) -> Cell {
-    let value: Result<serde_json::Value> = serde_json::from_str(try_and_log_ffi!(
-        amx,
-        CStr::from_ptr(string)
-            .to_str()
-            .chain_err(|| ffi_error("Invalid string. Can't create UTF-8 string"))
-    ))
-    .map_err(|e| ErrorKind::JSONError(e).into());
+    catch_panic_ffi!(amx, {
+        let name = try_and_log_ffi!(
+            amx,
+            str_from_ptr(name)
+                .chain_err(|| ffi_error("Invalid header name. Can't create UTF-8 string"))
+        );
 
-    match value {
-        Ok(value) => get_module_mut().json_handles.insert_with_unique_id(value),
-        Err(error) => {
-            use error_chain::ChainedError;
-            copy_unsafe_string!(amx, error_buffer, error.display_chain(), error_buffer_size);
-            0
-        }
-    }
+        try_to_get_response!(amx)
+            .headers
+            .get_all(name)
+            .iter()
+            .count() as Cell
+    })
 }
 
-// TODO: Remove copy-paste
 #[no_mangle]
-pub unsafe extern "C" fn grip_json_parse_file(
-    amx: *const c_void,
-    file: *mut c_char,
-    error_buffer: *mut c_char,
-    error_buffer_size: Cell,
-) -> Cell {
-    let value: Result<serde_json::Value> =
-        serde_json::from_reader(BufReader::new(try_and_log_ffi!(
+pub unsafe extern "C" fn grip_has_response_header(amx: *const c_void, name: *const c_char) -> Cell {
+    catch_panic_ffi!(amx, {
+        let name = try_and_log_ffi!(
             amx,
-            File::open(try_and_log_ffi!(
-                amx,
-                CStr::from_ptr(file)
-                    .to_str()
-                    .chain_err(|| ffi_error("Invalid string. Can't create UTF-8 string"))
-            ))
-            .chain_err(|| ffi_error("Can't open file."))
-        )))
-        .map_err(|e| ErrorKind::JSONError(e).into());
+            str_from_ptr(name)
+                .chain_err(|| ffi_error("Invalid header name. Can't create UTF-8 string"))
+        );
 
-    match value {
-        Ok(value) => get_module_mut().json_handles.insert_with_unique_id(value),
-        Err(error) => {
-            use error_chain::ChainedError;
-            copy_unsafe_string!(amx, error_buffer, error.display_chain(), error_buffer_size);
+        if try_to_get_response!(amx).headers.contains_key(name) {
+            1
+        } else {
             0
         }
-    }
+    })
 }
 
 #[no_mangle]
-pub unsafe extern "C" fn grip_json_equals(amx: *const c_void, value1: Cell, value2: Cell) -> Cell {
-    let value1 = try_and_log_ffi!(
-        amx,
-        get_module()
-            .json_handles
-            .get_with_id(value1)
-            .chain_err(|| ffi_error(format!("value1 handle {} is invalid", value1)))
-    );
-
-    let value2 = try_and_log_ffi!(
-        amx,
-        get_module()
-            .json_handles
-            .get_with_id(value2)
-            .chain_err(|| ffi_error(format!("value2 {} handle is invalid", value2)))
-    );
+pub unsafe extern "C" fn grip_destroy_json_value(amx: *const c_void, json_value: Cell) -> Cell {
+    catch_panic_ffi!(amx, {
+        try_and_log_ffi!(
+            amx,
+            get_module_mut()
+                .json_handles
+                .remove_with_id(json_value)
+                .chain_err(|| ffi_error(format!("Invalid json value handle {}", json_value)))
+        );
 
-    if value2 == value1 {
         1
-    } else {
-        0
-    }
+    })
 }
 
 #[no_mangle]
-pub unsafe extern "C" fn grip_json_get_type(amx: *const c_void, value: Cell) -> Cell {
-    match try_and_log_ffi!(
-        amx,
-        get_module()
-            .json_handles
-            .get_with_id(value)
-            .chain_err(|| ffi_error(format!("value {} handle is invalid", value)))
-    ) {
-        Value::Null => 1,
-        Value::String(_) => 2,
-        Value::Number(_) => 3,
-        Value::Object(_) => 4,
-        Value::Array(_) => 5,
-        Value::Bool(_) => 6,
-    }
+pub unsafe extern "C" fn grip_json_scope_begin(amx: *const c_void) -> Cell {
+    catch_panic_ffi!(amx, {
+        get_module_mut().json_handles.begin_scope(amx, false) as Cell
+    })
 }
 
 #[no_mangle]
-pub unsafe extern "C" fn grip_json_init_object() -> Cell {
-    get_module_mut()
-        .json_handles
-        .insert_with_unique_id(json!({}))
+pub unsafe extern "C" fn grip_json_scope_end(amx: *const c_void) -> Cell {
+    catch_panic_ffi!(amx, {
+        try_and_log_ffi!(
+            amx,
+            get_module_mut()
+                .json_handles
+                .end_scope(amx, false)
+                .chain_err(|| ffi_error("Can't end JSON handle scope"))
+        ) as Cell
+    })
 }
 
 #[no_mangle]
-pub unsafe extern "C" fn grip_json_init_array() -> Cell {
-    get_module_mut()
-        .json_handles
-        .insert_with_unique_id(json!([]))
+pub unsafe extern "C" fn grip_json_promote(amx: *const c_void, value: Cell) -> Cell {
+    catch_panic_ffi!(amx, {
+        try_and_log_ffi!(
+            amx,
+            get_module_mut()
+                .json_handles
+                .promote(value)
+                .chain_err(|| ffi_error(format!("Can't promote JSON value handle {}", value)))
+        );
+
+        1
+    })
 }
 
 #[no_mangle]
-pub unsafe extern "C" fn grip_json_init_string(amx: *const c_void, string: *mut c_char) -> Cell {
-    get_module_mut()
-        .json_handles
-        .insert_with_unique_id(json!(try_and_log_ffi!(
+pub unsafe extern "C" fn grip_json_freeze(amx: *const c_void, value: Cell) -> Cell {
+    catch_panic_ffi!(amx, {
+        try_and_log_ffi!(
             amx,
-            CStr::from_ptr(string)
-                .to_str()
-                .chain_err(|| ffi_error("Invalid string. Can't create UTF-8 string"))
+            get_module_mut()
+                .json_handles
+                .freeze(value)
+                .chain_err(|| ffi_error(format!("Can't freeze JSON value handle {}", value)))
+        );
+
+        1
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn grip_json_retain(amx: *const c_void, value: Cell) -> Cell {
+    catch_panic_ffi!(amx, {
+        try_and_log_ffi!(
+            amx,
+            get_module_mut()
+                .json_handles
+                .retain(amx, value)
+                .chain_err(|| ffi_error(format!("Can't retain JSON value handle {}", value)))
         )
-        .to_owned()))
+    })
 }
 
 #[no_mangle]
-pub unsafe extern "C" fn grip_json_init_number(value: Cell) -> Cell {
-    get_module_mut()
-        .json_handles
-        .insert_with_unique_id(json!(value))
+pub unsafe extern "C" fn grip_json_release(amx: *const c_void, value: Cell) -> Cell {
+    catch_panic_ffi!(amx, {
+        let json_handles = &mut get_module_mut().json_handles;
+        if !try_and_log_ffi!(amx, json_handles.is_frozen(value)) {
+            unconditionally_log_error!(
+                amx,
+                ffi_error(format!(
+                    "JSON value handle {} is not frozen, use grip_destroy_json_value",
+                    value
+                ))
+            );
+        }
+
+        try_and_log_ffi!(amx, json_handles.remove_with_id(value));
+
+        1
+    })
 }
 
 #[no_mangle]
-pub unsafe extern "C" fn grip_json_init_float(value: f64) -> Cell {
-    get_module_mut()
-        .json_handles
-        .insert_with_unique_id(json!(value))
+pub unsafe extern "C" fn grip_json_is_frozen(amx: *const c_void, value: Cell) -> Cell {
+    catch_panic_ffi!(amx, {
+        try_and_log_ffi!(amx, get_module().json_handles.is_frozen(value)) as Cell
+    })
 }
 
 #[no_mangle]
-pub unsafe extern "C" fn grip_json_init_bool(value: bool) -> Cell {
-    get_module_mut()
-        .json_handles
-        .insert_with_unique_id(json!(value))
+pub unsafe extern "C" fn grip_create_default_options(amx: *const c_void, timeout: f64) -> Cell {
+    catch_panic_ffi!(amx, {
+        use float_cmp::ApproxEq;
+
+        try_and_log_ffi!(
+            amx,
+            get_module_mut().options_handles.insert_with_unique_id(
+                amx,
+                RequestOptions::new(
+                    reqwest::header::HeaderMap::default(),
+                    try_and_log_ffi!(
+                        amx,
+                        if timeout.approx_eq(&-1.0, std::f64::EPSILON, 2) {
+                            Ok(None)
+                        } else if timeout >= 0.0 {
+                            Ok(Some(std::time::Duration::from_millis(
+                                (timeout * 1000.0) as u64,
+                            )))
+                        } else {
+                            Err(ffi_error(format!("Invalid timeout: {}", timeout)))
+                        }
+                    ),
+                    RetryPolicy::default(),
+                    RequestPriority::default(),
+                    None,
+                ),
+            )
+        )
+    })
 }
 
 #[no_mangle]
-pub unsafe extern "C" fn grip_json_init_null() -> Cell {
-    get_module_mut()
-        .json_handles
-        .insert_with_unique_id(json!(null))
+pub unsafe extern "C" fn grip_destroy_options(amx: *const c_void, options_handle: Cell) -> Cell {
+    catch_panic_ffi!(amx, {
+        try_and_log_ffi!(
+            amx,
+            get_module_mut()
+                .options_handles
+                .remove_with_id(options_handle)
+                .chain_err(|| ffi_error(format!("Invalid options handle {}", options_handle)))
+        );
+
+        1
+    })
 }
 
 #[no_mangle]
-pub unsafe extern "C" fn grip_json_get_string(
+pub unsafe extern "C" fn grip_options_add_header(
     amx: *const c_void,
-    value: Cell,
-    buffer: *mut c_char,
-    buffer_size: Cell,
+    options_handle: Cell,
+    header_name: *const c_char,
+    header_value: *const c_char,
 ) -> Cell {
-    match try_to_get_json_value!(amx, value) {
-        Value::String(s) => copy_unsafe_string!(amx, buffer, s, buffer_size),
-        v => unconditionally_log_error!(
+    catch_panic_ffi!(amx, {
+        let option = try_and_log_ffi!(
             amx,
-            ffi_error(format!("JSON Handle is not string. {:?}", v))
-        ),
-    }
-}
+            get_module_mut()
+                .options_handles
+                .get_mut_with_id(options_handle)
+                .chain_err(|| ffi_error(format!("Invalid options handle: {}", options_handle)))
+        );
 
-#[no_mangle]
-pub unsafe extern "C" fn grip_json_get_number(amx: *const c_void, value: Cell) -> Cell {
-    match try_to_get_json_value!(amx, value) {
-        Value::Number(n) => try_and_log_ffi!(
+        let header_name = try_and_log_ffi!(
+            amx,
+            CStr::from_ptr(header_name)
+                .to_str()
+                .chain_err(|| ffi_error("Invalid header name. Can't create UTF-8 string"))
+        );
+
+        let header_value = try_and_log_ffi!(
             amx,
-            n.as_i64().chain_err(|| ffi_error("Number is not integer"))
-        ) as Cell,
-        v => unconditionally_log_error!(
+            str_from_ptr(header_value)
+                .chain_err(|| ffi_error("Invalid header value. Can't create UTF-8 string"))
+        );
+
+        let header_value = try_and_log_ffi!(
             amx,
-            ffi_error(format!("JSON Handle is not number. {:?}", v))
-        ),
-    }
+            reqwest::header::HeaderValue::from_str(header_value)
+            .chain_err(|| ffi_error(format!("Header value contains invalid byte sequences or was rejected by Hyper HTTP implementation: {}", header_value)))
+        );
+
+        option.headers.insert(header_name, header_value);
+
+        1
+    })
 }
 
 #[no_mangle]
-pub unsafe extern "C" fn grip_json_get_float(
+pub unsafe extern "C" fn grip_options_set_retry(
     amx: *const c_void,
-    value: Cell,
-    ret: *mut f32,
+    options_handle: Cell,
+    max_attempts: Cell,
+    base_delay: f64,
+    max_delay: f64,
+    jitter: f64,
+    conditions: Cell,
 ) -> Cell {
-    *ret = 0.0;
+    catch_panic_ffi!(amx, {
+        let option = try_and_log_ffi!(
+            amx,
+            get_module_mut()
+                .options_handles
+                .get_mut_with_id(options_handle)
+                .chain_err(|| ffi_error(format!("Invalid options handle: {}", options_handle)))
+        );
 
-    match try_to_get_json_value!(amx, value) {
-        Value::Number(n) => {
-            *ret = try_and_log_ffi!(
+        if max_attempts < 1 {
+            unconditionally_log_error!(
                 amx,
-                n.as_f64()
-                    .chain_err(|| ffi_error("Number is not 32 bit float"))
-            ) as f32;
-            1
+                ffi_error(format!(
+                    "Max attempts {} should be at least 1",
+                    max_attempts
+                ))
+            );
         }
-        v => unconditionally_log_error!(
-            amx,
-            ffi_error(format!("JSON Handle is not number. {:?}", v))
-        ),
-    }
+
+        if base_delay < 0.0 || max_delay < base_delay {
+            unconditionally_log_error!(
+                amx,
+                ffi_error(format!(
+                    "Invalid retry delays: base {}, max {}",
+                    base_delay, max_delay
+                ))
+            );
+        }
+
+        if !(0.0..=1.0).contains(&jitter) {
+            unconditionally_log_error!(
+                amx,
+                ffi_error(format!("Jitter {} should be from 0.0 to 1.0", jitter))
+            );
+        }
+
+        option.retry = RetryPolicy {
+            max_attempts: max_attempts as usize,
+            base_delay: std::time::Duration::from_millis((base_delay * 1000.0) as u64),
+            max_delay: std::time::Duration::from_millis((max_delay * 1000.0) as u64),
+            jitter,
+            conditions: RetryConditions {
+                connection_errors: conditions & 1 != 0,
+                timeouts: conditions & 2 != 0,
+                server_errors: conditions & 4 != 0,
+                too_many_requests: conditions & 8 != 0,
+            },
+        };
+
+        1
+    })
 }
 
 #[no_mangle]
-pub unsafe extern "C" fn grip_json_get_bool(amx: *const c_void, value: Cell) -> Cell {
-    match try_to_get_json_value!(amx, value) {
-        Value::Bool(b) => *b as Cell,
-        v => unconditionally_log_error!(
+pub unsafe extern "C" fn grip_options_set_priority(
+    amx: *const c_void,
+    options_handle: Cell,
+    priority: Cell,
+) -> Cell {
+    catch_panic_ffi!(amx, {
+        let option = try_and_log_ffi!(
             amx,
-            ffi_error(format!("JSON Handle is not bool. {:?}", v))
-        ),
-    }
+            get_module_mut()
+                .options_handles
+                .get_mut_with_id(options_handle)
+                .chain_err(|| ffi_error(format!("Invalid options handle: {}", options_handle)))
+        );
+
+        option.priority = match priority {
+            0 => RequestPriority::Low,
+            1 => RequestPriority::Normal,
+            2 => RequestPriority::High,
+            _ => unconditionally_log_error!(
+                amx,
+                ffi_error(format!("Invalid request priority {}", priority))
+            ),
+        };
+
+        1
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn grip_options_set_rate_limit_group(
+    amx: *const c_void,
+    options_handle: Cell,
+    group: *const c_char,
+) -> Cell {
+    catch_panic_ffi!(amx, {
+        let group = try_and_log_ffi!(
+            amx,
+            str_from_ptr(group)
+                .chain_err(|| ffi_error("Invalid rate limit group. Can't create UTF-8 string"))
+        );
+
+        if !group.is_empty() && !get_module().config.rate_limit_groups.contains_key(group) {
+            unconditionally_log_error!(
+                amx,
+                ffi_error(format!(
+                    "Unknown rate limit group `{}`. Groups are defined in grip.ini",
+                    group
+                ))
+            );
+        }
+
+        let option = try_and_log_ffi!(
+            amx,
+            get_module_mut()
+                .options_handles
+                .get_mut_with_id(options_handle)
+                .chain_err(|| ffi_error(format!("Invalid options handle: {}", options_handle)))
+        );
+
+        option.rate_limit_group = if group.is_empty() {
+            None
+        } else {
+            Some(group.to_owned())
+        };
+
+        1
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn grip_process_request() {
+    catch_panic_ffi!(std::ptr::null(), (), {
+        logger::flush();
+
+        let pending_requests = get_module().global_queue.number_of_pending_requests();
+
+        // Backlog is compensated by executing more callbacks and spending more time per frame.
+        let multiplier = std::cmp::max(pending_requests / 500, 1);
+        if multiplier > std::cmp::max(get_module().frame_stats.multiplier, 1) {
+            warn!("More than 500 requests are pending.. Fastening execution {} times to compensate that", multiplier);
+        }
+
+        let budget = match get_module().config.frame_budget_microseconds {
+            0 => None,
+            budget => Some(std::time::Duration::from_micros(
+                (budget * multiplier) as u64,
+            )),
+        };
+
+        if let Some(execution) = get_module_mut().global_queue.execute_queue_with_limit(
+            get_module().config.callbacks_per_frame * multiplier,
+            budget,
+            std::time::Duration::from_micros(
+                get_module().config.microseconds_delay_between_attempts as u64,
+            ),
+        ) {
+            get_module_mut().frame_stats = FrameStats {
+                execution,
+                pending_requests,
+                waiting_requests: get_module().global_queue.number_of_waiting_requests(),
+                in_flight_requests: get_module().global_queue.number_of_in_flight_requests(),
+                multiplier,
+            };
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn grip_get_frame_stat(amx: *const c_void, stat: Cell) -> Cell {
+    catch_panic_ffi!(amx, {
+        let stats = &get_module().frame_stats;
+        match stat {
+            0 => stats.execution.executed as Cell,
+            1 => stats.execution.elapsed.as_micros() as Cell,
+            2 => stats.pending_requests as Cell,
+            3 => stats.multiplier as Cell,
+            4 => stats.waiting_requests as Cell,
+            5 => stats.in_flight_requests as Cell,
+            _ => unconditionally_log_error!(amx, ffi_error(format!("Invalid frame stat {}", stat))),
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn grip_json_parse_response_body(
+    amx: *const c_void,
+    error_buffer: *mut c_char,
+    error_buffer_size: Cell,
+) -> Cell {
+    catch_panic_ffi!(amx, {
+        if let Ok(response) = try_and_log_ffi!(
+            amx,
+            get_module()
+                .current_response
+                .as_ref()
+                .chain_err(|| ffi_error("No active response at this time"))
+        ) {
+            let value: Result<serde_json::Value> = serde_json::from_slice(&response.body[..])
+                .map_err(|e| ErrorKind::JSONError(e).into());
+
+            match value {
+                Ok(value) => try_and_log_ffi!(
+                    amx,
+                    get_module_mut()
+                        .json_handles
+                        .insert_with_unique_id(amx, value)
+                ),
+                Err(error) => {
+                    use error_chain::ChainedError;
+                    copy_unsafe_string!(
+                        amx,
+                        error_buffer,
+                        error.display_chain(),
+                        error_buffer_size
+                    );
+                    0
+                }
+            }
+        } else {
+            unconditionally_log_error!(
+                amx,
+                ffi_error("Error/Cancellation/Timeout occurred for this response.")
+            )
+        }
+    })
+}
+
+// TODO: Remove copy-paste
+#[no_mangle]
+pub unsafe extern "C" fn grip_json_parse_string(
+    amx: *const c_void,
+    string: *mut c_char,
+    error_buffer: *mut c_char,
+    error_buffer_size: Cell,
+) -> Cell {
+    catch_panic_ffi!(amx, {
+        let value: Result<serde_json::Value> = serde_json::from_str(try_and_log_ffi!(
+            amx,
+            CStr::from_ptr(string)
+                .to_str()
+                .chain_err(|| ffi_error("Invalid string. Can't create UTF-8 string"))
+        ))
+        .map_err(|e| ErrorKind::JSONError(e).into());
+
+        match value {
+            Ok(value) => try_and_log_ffi!(
+                amx,
+                get_module_mut()
+                    .json_handles
+                    .insert_with_unique_id(amx, value)
+            ),
+            Err(error) => {
+                use error_chain::ChainedError;
+                copy_unsafe_string!(amx, error_buffer, error.display_chain(), error_buffer_size);
+                0
+            }
+        }
+    })
+}
+
+// TODO: Remove copy-paste
+#[no_mangle]
+pub unsafe extern "C" fn grip_json_parse_file(
+    amx: *const c_void,
+    file: *mut c_char,
+    error_buffer: *mut c_char,
+    error_buffer_size: Cell,
+) -> Cell {
+    catch_panic_ffi!(amx, {
+        let value: Result<serde_json::Value> =
+            serde_json::from_reader(BufReader::new(try_and_log_ffi!(
+                amx,
+                File::open(try_and_log_ffi!(
+                    amx,
+                    CStr::from_ptr(file)
+                        .to_str()
+                        .chain_err(|| ffi_error("Invalid string. Can't create UTF-8 string"))
+                ))
+                .chain_err(|| ffi_error("Can't open file."))
+            )))
+            .map_err(|e| ErrorKind::JSONError(e).into());
+
+        match value {
+            Ok(value) => try_and_log_ffi!(
+                amx,
+                get_module_mut()
+                    .json_handles
+                    .insert_with_unique_id(amx, value)
+            ),
+            Err(error) => {
+                use error_chain::ChainedError;
+                copy_unsafe_string!(amx, error_buffer, error.display_chain(), error_buffer_size);
+                0
+            }
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn grip_json_serial_size(
+    amx: *const c_void,
+    value: Cell,
+    pretty: bool,
+    null_byte: bool,
+) -> Cell {
+    catch_panic_ffi!(amx, {
+        let size = try_and_log_ffi!(
+            amx,
+            serialize_json(try_to_get_json_value!(amx, value), pretty)
+                .chain_err(|| ffi_error(format!("Can't serialize JSON value {}", value)))
+        )
+        .len();
+
+        (if null_byte { size + 1 } else { size }) as Cell
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn grip_json_serial_to_string(
+    amx: *const c_void,
+    value: Cell,
+    buffer: *mut c_char,
+    maxlen: Cell,
+    pretty: bool,
+) -> Cell {
+    catch_panic_ffi!(amx, {
+        copy_unsafe_string!(
+            amx,
+            buffer,
+            try_and_log_ffi!(
+                amx,
+                serialize_json(try_to_get_json_value!(amx, value), pretty)
+                    .chain_err(|| ffi_error(format!("Can't serialize JSON value {}", value)))
+            ),
+            maxlen
+        )
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn grip_json_serial_to_file(
+    amx: *const c_void,
+    value: Cell,
+    file: *const c_char,
+    pretty: bool,
+) -> Cell {
+    catch_panic_ffi!(amx, {
+        let value = try_to_get_json_value!(amx, value);
+        let path = try_and_log_ffi!(
+            amx,
+            CStr::from_ptr(file)
+                .to_str()
+                .chain_err(|| ffi_error("Invalid string. Can't create UTF-8 string"))
+        );
+
+        let data: Result<Vec<u8>> = if pretty {
+            serde_json::to_vec_pretty(value)
+        } else {
+            serde_json::to_vec(value)
+        }
+        .map_err(|e| ErrorKind::JSONError(e).into());
+        let data = try_and_log_ffi!(amx, data);
+
+        // Document is written next to the file and renamed over it,
+        // so the existing file is kept if writing fails.
+        let temp_path = format!("{}.tmp", path);
+        let written =
+            std::fs::write(&temp_path, &data).and_then(|_| std::fs::rename(&temp_path, path));
+        if written.is_err() {
+            std::fs::remove_file(&temp_path).ok();
+        }
+
+        try_and_log_ffi!(
+            amx,
+            written.chain_err(|| ffi_error(format!("Can't write to the file {}.", path)))
+        );
+
+        1
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn grip_json_equals(amx: *const c_void, value1: Cell, value2: Cell) -> Cell {
+    catch_panic_ffi!(amx, {
+        let value1 = try_and_log_ffi!(
+            amx,
+            get_module()
+                .json_handles
+                .get_with_id(value1)
+                .chain_err(|| ffi_error(format!("value1 handle {} is invalid", value1)))
+        );
+
+        let value2 = try_and_log_ffi!(
+            amx,
+            get_module()
+                .json_handles
+                .get_with_id(value2)
+                .chain_err(|| ffi_error(format!("value2 {} handle is invalid", value2)))
+        );
+
+        if value2 == value1 {
+            1
+        } else {
+            0
+        }
+    })
+}
+
+fn json_type(value: &Value) -> Cell {
+    match value {
+        Value::Null => 1,
+        Value::String(_) => 2,
+        Value::Number(_) => 3,
+        Value::Object(_) => 4,
+        Value::Array(_) => 5,
+        Value::Bool(_) => 6,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn grip_json_get_type(amx: *const c_void, value: Cell) -> Cell {
+    catch_panic_ffi!(amx, {
+        json_type(try_and_log_ffi!(
+            amx,
+            get_module()
+                .json_handles
+                .get_with_id(value)
+                .chain_err(|| ffi_error(format!("value {} handle is invalid", value)))
+        ))
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn grip_json_query(
+    amx: *const c_void,
+    value: Cell,
+    path: *const c_char,
+) -> Cell {
+    catch_panic_ffi!(amx, {
+        let result = try_and_log_ffi!(
+            amx,
+            query_json(
+                try_to_get_json_value!(amx, value),
+                try_and_log_ffi!(amx, str_from_ptr(path))
+            )
+        );
+
+        try_and_log_ffi!(
+            amx,
+            get_module_mut()
+                .json_handles
+                .insert_with_unique_id(amx, result)
+        )
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn grip_json_merge_patch(
+    amx: *const c_void,
+    value: Cell,
+    patch: Cell,
+) -> Cell {
+    catch_panic_ffi!(amx, {
+        let patch = try_to_get_json_value!(amx, patch).clone();
+        json_patch::merge(try_to_get_json_value_mut!(amx, value), &patch);
+
+        1
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn grip_json_patch(amx: *const c_void, value: Cell, patch: Cell) -> Cell {
+    catch_panic_ffi!(amx, {
+        let patch = try_to_get_json_value!(amx, patch).clone();
+        try_and_log_ffi!(
+            amx,
+            apply_json_patch(try_to_get_json_value_mut!(amx, value), &patch)
+        );
+
+        1
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn grip_json_diff(amx: *const c_void, from: Cell, to: Cell) -> Cell {
+    catch_panic_ffi!(amx, {
+        let patch = try_and_log_ffi!(
+            amx,
+            diff_json(
+                try_to_get_json_value!(amx, from),
+                try_to_get_json_value!(amx, to)
+            )
+        );
+
+        try_and_log_ffi!(
+            amx,
+            get_module_mut()
+                .json_handles
+                .insert_with_unique_id(amx, patch)
+        )
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn grip_json_init_object(amx: *const c_void) -> Cell {
+    catch_panic_ffi!(amx, {
+        try_and_log_ffi!(
+            amx,
+            get_module_mut()
+                .json_handles
+                .insert_with_unique_id(amx, json!({}))
+        )
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn grip_json_init_array(amx: *const c_void) -> Cell {
+    catch_panic_ffi!(amx, {
+        try_and_log_ffi!(
+            amx,
+            get_module_mut()
+                .json_handles
+                .insert_with_unique_id(amx, json!([]))
+        )
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn grip_json_init_string(amx: *const c_void, string: *mut c_char) -> Cell {
+    catch_panic_ffi!(amx, {
+        try_and_log_ffi!(
+            amx,
+            get_module_mut().json_handles.insert_with_unique_id(
+                amx,
+                json!(try_and_log_ffi!(
+                    amx,
+                    CStr::from_ptr(string)
+                        .to_str()
+                        .chain_err(|| ffi_error("Invalid string. Can't create UTF-8 string"))
+                )
+                .to_owned()),
+            )
+        )
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn grip_json_init_number(amx: *const c_void, value: Cell) -> Cell {
+    catch_panic_ffi!(amx, {
+        try_and_log_ffi!(
+            amx,
+            get_module_mut()
+                .json_handles
+                .insert_with_unique_id(amx, json!(value))
+        )
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn grip_json_init_float(amx: *const c_void, value: f64) -> Cell {
+    catch_panic_ffi!(amx, {
+        try_and_log_ffi!(
+            amx,
+            get_module_mut()
+                .json_handles
+                .insert_with_unique_id(amx, json!(value))
+        )
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn grip_json_init_bool(amx: *const c_void, value: bool) -> Cell {
+    catch_panic_ffi!(amx, {
+        try_and_log_ffi!(
+            amx,
+            get_module_mut()
+                .json_handles
+                .insert_with_unique_id(amx, json!(value))
+        )
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn grip_json_init_null(amx: *const c_void) -> Cell {
+    catch_panic_ffi!(amx, {
+        try_and_log_ffi!(
+            amx,
+            get_module_mut()
+                .json_handles
+                .insert_with_unique_id(amx, json!(null))
+        )
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn grip_json_get_string(
+    amx: *const c_void,
+    value: Cell,
+    buffer: *mut c_char,
+    buffer_size: Cell,
+) -> Cell {
+    catch_panic_ffi!(amx, {
+        match try_to_get_json_value!(amx, value) {
+            Value::String(s) => copy_unsafe_string!(amx, buffer, s, buffer_size),
+            v => unconditionally_log_error!(
+                amx,
+                ffi_error(format!("JSON Handle is not string. {:?}", v))
+            ),
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn grip_json_get_number(amx: *const c_void, value: Cell) -> Cell {
+    catch_panic_ffi!(amx, {
+        match try_to_get_json_value!(amx, value) {
+            Value::Number(n) => try_and_log_ffi!(
+                amx,
+                n.as_i64().chain_err(|| ffi_error("Number is not integer"))
+            ) as Cell,
+            v => unconditionally_log_error!(
+                amx,
+                ffi_error(format!("JSON Handle is not number. {:?}", v))
+            ),
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn grip_json_get_float(
+    amx: *const c_void,
+    value: Cell,
+    ret: *mut f32,
+) -> Cell {
+    catch_panic_ffi!(amx, {
+        *ret = 0.0;
+
+        match try_to_get_json_value!(amx, value) {
+            Value::Number(n) => {
+                *ret = try_and_log_ffi!(
+                    amx,
+                    n.as_f64()
+                        .chain_err(|| ffi_error("Number is not 32 bit float"))
+                ) as f32;
+                1
+            }
+            v => unconditionally_log_error!(
+                amx,
+                ffi_error(format!("JSON Handle is not number. {:?}", v))
+            ),
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn grip_json_get_bool(amx: *const c_void, value: Cell) -> Cell {
+    catch_panic_ffi!(amx, {
+        match try_to_get_json_value!(amx, value) {
+            Value::Bool(b) => *b as Cell,
+            v => unconditionally_log_error!(
+                amx,
+                ffi_error(format!("JSON Handle is not bool. {:?}", v))
+            ),
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn grip_json_array_get_value(
+    amx: *const c_void,
+    array: Cell,
+    index: Cell,
+) -> Cell {
+    catch_panic_ffi!(amx, {
+        match try_to_get_json_value!(amx, array) {
+            Value::Array(vec) => try_and_log_ffi!(
+                amx,
+                get_module_mut()
+                    .json_handles
+                    .insert_with_unique_id(amx, vec[try_as_array_index!(amx, vec, index)].clone())
+            ),
+            v => {
+                unconditionally_log_error!(
+                    amx,
+                    ffi_error(format!("JSON Handle is not array. {:?}", v))
+                )
+            }
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn grip_json_array_get_child(
+    amx: *const c_void,
+    array: Cell,
+    index: Cell,
+) -> Cell {
+    catch_panic_ffi!(amx, {
+        match try_to_get_json_value!(amx, array) {
+            Value::Array(_) => (),
+            v => {
+                unconditionally_log_error!(
+                    amx,
+                    ffi_error(format!("JSON Handle is not array. {:?}", v))
+                )
+            }
+        }
+
+        let pointer = format!("/{}", try_as_usize!(amx, index));
+
+        try_and_log_ffi!(
+            amx,
+            get_module_mut()
+                .json_handles
+                .insert_child(amx, array, &pointer)
+                .chain_err(|| ffi_error(format!("Can't create child of JSON handle {}", array)))
+        )
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn grip_json_array_get_string(
+    amx: *const c_void,
+    array: Cell,
+    index: Cell,
+    buffer: *mut c_char,
+    buffer_size: Cell,
+) -> Cell {
+    catch_panic_ffi!(amx, {
+        match try_to_get_json_value!(amx, array) {
+            Value::Array(vec) => match &vec[try_as_array_index!(amx, vec, index)] {
+                Value::String(s) => copy_unsafe_string!(amx, buffer, s, buffer_size),
+                v => unconditionally_log_error!(
+                    amx,
+                    ffi_error(format!("JSON Handle is not string. {:?}", v))
+                ),
+            },
+            v => {
+                unconditionally_log_error!(
+                    amx,
+                    ffi_error(format!("JSON Handle is not array. {:?}", v))
+                )
+            }
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn grip_json_array_get_number(
+    amx: *const c_void,
+    array: Cell,
+    index: Cell,
+) -> Cell {
+    catch_panic_ffi!(amx, {
+        match try_to_get_json_value!(amx, array) {
+            Value::Array(vec) => match &vec[try_as_array_index!(amx, vec, index)] {
+                Value::Number(n) => try_and_log_ffi!(
+                    amx,
+                    n.as_i64().chain_err(|| ffi_error("Number is not integer"))
+                ) as Cell,
+                v => unconditionally_log_error!(
+                    amx,
+                    ffi_error(format!("JSON Handle is not number. {:?}", v))
+                ),
+            },
+            v => {
+                unconditionally_log_error!(
+                    amx,
+                    ffi_error(format!("JSON Handle is not array. {:?}", v))
+                )
+            }
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn grip_json_array_get_float(
+    amx: *const c_void,
+    array: Cell,
+    index: Cell,
+    ret: *mut f32,
+) -> Cell {
+    catch_panic_ffi!(amx, {
+        match try_to_get_json_value!(amx, array) {
+            Value::Array(vec) => match &vec[try_as_array_index!(amx, vec, index)] {
+                Value::Number(n) => {
+                    *ret = try_and_log_ffi!(
+                        amx,
+                        n.as_f64()
+                            .chain_err(|| ffi_error("Number is not 32 bit float"))
+                    ) as f32;
+
+                    1
+                }
+                v => unconditionally_log_error!(
+                    amx,
+                    ffi_error(format!("JSON Handle is not number. {:?}", v))
+                ),
+            },
+            v => {
+                unconditionally_log_error!(
+                    amx,
+                    ffi_error(format!("JSON Handle is not array. {:?}", v))
+                )
+            }
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn grip_json_array_get_bool(
+    amx: *const c_void,
+    array: Cell,
+    index: Cell,
+) -> Cell {
+    catch_panic_ffi!(amx, {
+        match try_to_get_json_value!(amx, array) {
+            Value::Array(vec) => match &vec[try_as_array_index!(amx, vec, index)] {
+                Value::Bool(b) => *b as Cell,
+                v => unconditionally_log_error!(
+                    amx,
+                    ffi_error(format!("JSON Handle is not number. {:?}", v))
+                ),
+            },
+            v => {
+                unconditionally_log_error!(
+                    amx,
+                    ffi_error(format!("JSON Handle is not array. {:?}", v))
+                )
+            }
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn grip_json_array_get_count(amx: *const c_void, array: Cell) -> Cell {
+    catch_panic_ffi!(amx, {
+        match try_to_get_json_value!(amx, array) {
+            Value::Array(vec) => vec.len() as Cell,
+            v => {
+                unconditionally_log_error!(
+                    amx,
+                    ffi_error(format!("JSON Handle is not array. {:?}", v))
+                )
+            }
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn grip_json_array_replace_value(
+    amx: *const c_void,
+    array: Cell,
+    index: Cell,
+    value: Cell,
+) -> Cell {
+    catch_panic_ffi!(amx, {
+        match try_to_get_json_value_mut!(amx, array) {
+            Value::Array(vec) => {
+                let index = try_as_array_index!(amx, vec, index);
+                vec[index] = try_to_get_json_value!(amx, value).clone();
+                1
+            }
+            v => {
+                unconditionally_log_error!(
+                    amx,
+                    ffi_error(format!("JSON Handle is not array. {:?}", v))
+                )
+            }
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn grip_json_array_replace_string(
+    amx: *const c_void,
+    array: Cell,
+    index: Cell,
+    string: *const c_char,
+) -> Cell {
+    catch_panic_ffi!(amx, {
+        match try_to_get_json_value_mut!(amx, array) {
+            Value::Array(vec) => {
+                let index = try_as_array_index!(amx, vec, index);
+                vec[index] = json!(try_and_log_ffi!(
+                    amx,
+                    CStr::from_ptr(string)
+                        .to_str()
+                        .chain_err(|| ffi_error("Invalid string. Can't create UTF-8 string"))
+                )
+                .to_owned());
+                1
+            }
+            v => {
+                unconditionally_log_error!(
+                    amx,
+                    ffi_error(format!("JSON Handle is not array. {:?}", v))
+                )
+            }
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn grip_json_array_replace_number(
+    amx: *const c_void,
+    array: Cell,
+    index: Cell,
+    value: Cell,
+) -> Cell {
+    catch_panic_ffi!(amx, {
+        match try_to_get_json_value_mut!(amx, array) {
+            Value::Array(vec) => {
+                let index = try_as_array_index!(amx, vec, index);
+                vec[index] = json!(value);
+                1
+            }
+            v => {
+                unconditionally_log_error!(
+                    amx,
+                    ffi_error(format!("JSON Handle is not array. {:?}", v))
+                )
+            }
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn grip_json_array_replace_float(
+    amx: *const c_void,
+    array: Cell,
+    index: Cell,
+    value: f32,
+) -> Cell {
+    catch_panic_ffi!(amx, {
+        match try_to_get_json_value_mut!(amx, array) {
+            Value::Array(vec) => {
+                let index = try_as_array_index!(amx, vec, index);
+                vec[index] = json!(value);
+                1
+            }
+            v => {
+                unconditionally_log_error!(
+                    amx,
+                    ffi_error(format!("JSON Handle is not array. {:?}", v))
+                )
+            }
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn grip_json_array_replace_bool(
+    amx: *const c_void,
+    array: Cell,
+    index: Cell,
+    value: bool,
+) -> Cell {
+    catch_panic_ffi!(amx, {
+        match try_to_get_json_value_mut!(amx, array) {
+            Value::Array(vec) => {
+                let index = try_as_array_index!(amx, vec, index);
+                vec[index] = json!(value);
+                1
+            }
+            v => {
+                unconditionally_log_error!(
+                    amx,
+                    ffi_error(format!("JSON Handle is not array. {:?}", v))
+                )
+            }
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn grip_json_array_replace_null(
+    amx: *const c_void,
+    array: Cell,
+    index: Cell,
+) -> Cell {
+    catch_panic_ffi!(amx, {
+        match try_to_get_json_value_mut!(amx, array) {
+            Value::Array(vec) => {
+                let index = try_as_array_index!(amx, vec, index);
+                vec[index] = json!(null);
+                1
+            }
+            v => {
+                unconditionally_log_error!(
+                    amx,
+                    ffi_error(format!("JSON Handle is not array. {:?}", v))
+                )
+            }
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn grip_json_array_append_value(
+    amx: *const c_void,
+    array: Cell,
+    value: Cell,
+) -> Cell {
+    catch_panic_ffi!(amx, {
+        match try_to_get_json_value_mut!(amx, array) {
+            Value::Array(vec) => {
+                vec.push(try_to_get_json_value!(amx, value).clone());
+                1
+            }
+            v => {
+                unconditionally_log_error!(
+                    amx,
+                    ffi_error(format!("JSON Handle is not array. {:?}", v))
+                )
+            }
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn grip_json_array_append_string(
+    amx: *const c_void,
+    array: Cell,
+    string: *const c_char,
+) -> Cell {
+    catch_panic_ffi!(amx, {
+        match try_to_get_json_value_mut!(amx, array) {
+            Value::Array(vec) => {
+                vec.push(json!(try_and_log_ffi!(
+                    amx,
+                    CStr::from_ptr(string)
+                        .to_str()
+                        .chain_err(|| ffi_error("Invalid string. Can't create UTF-8 string"))
+                )
+                .to_owned()));
+                1
+            }
+            v => {
+                unconditionally_log_error!(
+                    amx,
+                    ffi_error(format!("JSON Handle is not array. {:?}", v))
+                )
+            }
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn grip_json_array_append_number(
+    amx: *const c_void,
+    array: Cell,
+    value: Cell,
+) -> Cell {
+    catch_panic_ffi!(amx, {
+        match try_to_get_json_value_mut!(amx, array) {
+            Value::Array(vec) => {
+                vec.push(json!(value));
+                1
+            }
+            v => {
+                unconditionally_log_error!(
+                    amx,
+                    ffi_error(format!("JSON Handle is not array. {:?}", v))
+                )
+            }
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn grip_json_array_append_float(
+    amx: *const c_void,
+    array: Cell,
+    value: f32,
+) -> Cell {
+    catch_panic_ffi!(amx, {
+        match try_to_get_json_value_mut!(amx, array) {
+            Value::Array(vec) => {
+                vec.push(json!(value));
+                1
+            }
+            v => {
+                unconditionally_log_error!(
+                    amx,
+                    ffi_error(format!("JSON Handle is not array. {:?}", v))
+                )
+            }
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn grip_json_array_append_bool(
+    amx: *const c_void,
+    array: Cell,
+    value: bool,
+) -> Cell {
+    catch_panic_ffi!(amx, {
+        match try_to_get_json_value_mut!(amx, array) {
+            Value::Array(vec) => {
+                vec.push(json!(value));
+                1
+            }
+            v => {
+                unconditionally_log_error!(
+                    amx,
+                    ffi_error(format!("JSON Handle is not array. {:?}", v))
+                )
+            }
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn grip_json_array_append_null(amx: *const c_void, array: Cell) -> Cell {
+    catch_panic_ffi!(amx, {
+        match try_to_get_json_value_mut!(amx, array) {
+            Value::Array(vec) => {
+                vec.push(json!(null));
+                1
+            }
+            v => {
+                unconditionally_log_error!(
+                    amx,
+                    ffi_error(format!("JSON Handle is not array. {:?}", v))
+                )
+            }
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn grip_json_array_remove(
+    amx: *const c_void,
+    array: Cell,
+    index: Cell,
+) -> Cell {
+    catch_panic_ffi!(amx, {
+        match try_to_get_json_value_mut!(amx, array) {
+            Value::Array(vec) => {
+                vec.remove(try_as_array_index!(amx, vec, index));
+                1
+            }
+            v => {
+                unconditionally_log_error!(
+                    amx,
+                    ffi_error(format!("JSON Handle is not array. {:?}", v))
+                )
+            }
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn grip_json_array_clear(amx: *const c_void, array: Cell) -> Cell {
+    catch_panic_ffi!(amx, {
+        match try_to_get_json_value_mut!(amx, array) {
+            Value::Array(vec) => {
+                vec.clear();
+                1
+            }
+            v => {
+                unconditionally_log_error!(
+                    amx,
+                    ffi_error(format!("JSON Handle is not array. {:?}", v))
+                )
+            }
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn grip_json_object_get_value(
+    amx: *const c_void,
+    object: Cell,
+    name: *const c_char,
+    dot_notation: bool,
+) -> Cell {
+    catch_panic_ffi!(amx, {
+        try_and_log_ffi!(
+            amx,
+            get_module_mut().json_handles.insert_with_unique_id(
+                amx,
+                try_to_get_json_object_value!(amx, object, name, dot_notation).clone(),
+            )
+        )
+    })
+}
+#[no_mangle]
+pub unsafe extern "C" fn grip_json_object_get_child(
+    amx: *const c_void,
+    object: Cell,
+    name: *const c_char,
+    dot_notation: bool,
+) -> Cell {
+    catch_panic_ffi!(amx, {
+        match try_to_get_json_value!(amx, object) {
+            Value::Object(_) => (),
+            v => unconditionally_log_error!(
+                amx,
+                ffi_error(format!("JSON Handle is not object. {:?}", v))
+            ),
+        }
+
+        let pointer = try_and_log_ffi!(
+            amx,
+            selective_to_pointer(try_and_log_ffi!(amx, str_from_ptr(name)), dot_notation)
+        );
+
+        try_and_log_ffi!(
+            amx,
+            get_module_mut()
+                .json_handles
+                .insert_child(amx, object, &pointer)
+                .chain_err(|| ffi_error(format!("Can't create child of JSON handle {}", object)))
+        )
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn grip_json_object_get_string(
+    amx: *const c_void,
+    object: Cell,
+    name: *const c_char,
+    buffer: *mut c_char,
+    maxlen: Cell,
+    dot_notation: bool,
+) -> Cell {
+    catch_panic_ffi!(amx, {
+        match try_to_get_json_object_value!(amx, object, name, dot_notation) {
+            Value::String(s) => copy_unsafe_string!(amx, buffer, s, maxlen),
+            v => unconditionally_log_error!(
+                amx,
+                ffi_error(format!("JSON Handle is not string. {:?}", v))
+            ),
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn grip_json_object_get_number(
+    amx: *const c_void,
+    object: Cell,
+    name: *const c_char,
+    dot_notation: bool,
+) -> Cell {
+    catch_panic_ffi!(amx, {
+        match try_to_get_json_object_value!(amx, object, name, dot_notation) {
+            Value::Number(n) => try_and_log_ffi!(
+                amx,
+                n.as_i64().chain_err(|| ffi_error("Number is not integer"))
+            ) as Cell,
+            v => unconditionally_log_error!(
+                amx,
+                ffi_error(format!("JSON Handle is not number. {:?}", v))
+            ),
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn grip_json_object_get_float(
+    amx: *const c_void,
+    object: Cell,
+    name: *const c_char,
+    dot_notation: bool,
+    ret: *mut f32,
+) -> Cell {
+    catch_panic_ffi!(amx, {
+        match try_to_get_json_object_value!(amx, object, name, dot_notation) {
+            Value::Number(n) => {
+                *ret = try_and_log_ffi!(
+                    amx,
+                    n.as_f64().chain_err(|| ffi_error("Number is not float"))
+                ) as f32;
+
+                1
+            }
+            v => unconditionally_log_error!(
+                amx,
+                ffi_error(format!("JSON Handle is not number. {:?}", v))
+            ),
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn grip_json_object_get_bool(
+    amx: *const c_void,
+    object: Cell,
+    name: *const c_char,
+    dot_notation: bool,
+) -> Cell {
+    catch_panic_ffi!(amx, {
+        match try_to_get_json_object_value!(amx, object, name, dot_notation) {
+            Value::Bool(b) => *b as Cell,
+            v => unconditionally_log_error!(
+                amx,
+                ffi_error(format!("JSON Handle is not bool. {:?}", v))
+            ),
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn grip_json_object_get_count(amx: *const c_void, object: Cell) -> Cell {
+    catch_panic_ffi!(amx, {
+        match try_to_get_json_value!(amx, object) {
+            Value::Object(m) => m.len() as Cell,
+            v => unconditionally_log_error!(
+                amx,
+                ffi_error(format!("JSON Handle is not object. {:?}", v))
+            ),
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn grip_json_object_get_name(
+    amx: *const c_void,
+    object: Cell,
+    index: Cell,
+    buffer: *mut c_char,
+    maxlen: Cell,
+) -> Cell {
+    catch_panic_ffi!(amx, {
+        match try_to_get_json_value!(amx, object) {
+            Value::Object(m) => copy_unsafe_string!(
+                amx,
+                buffer,
+                try_and_log_ffi!(
+                    amx,
+                    m.keys()
+                        .nth(try_as_usize!(amx, index))
+                        .chain_err(|| ffi_error(format!(
+                            "Object index {} is out of bounds",
+                            index
+                        )))
+                ),
+                maxlen
+            ),
+            v => unconditionally_log_error!(
+                amx,
+                ffi_error(format!("JSON Handle is not object. {:?}", v))
+            ),
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn grip_json_object_get_value_at(
+    amx: *const c_void,
+    object: Cell,
+    index: Cell,
+) -> Cell {
+    catch_panic_ffi!(amx, {
+        match try_to_get_json_value!(amx, object) {
+            Value::Object(m) => try_and_log_ffi!(
+                amx,
+                get_module_mut().json_handles.insert_with_unique_id(
+                    amx,
+                    try_and_log_ffi!(
+                        amx,
+                        m.values()
+                            .nth(try_as_usize!(amx, index))
+                            .chain_err(|| ffi_error(format!(
+                                "Object index {} is out of bounds",
+                                index
+                            )))
+                    )
+                    .clone(),
+                )
+            ),
+            v => unconditionally_log_error!(
+                amx,
+                ffi_error(format!("JSON Handle is not object. {:?}", v))
+            ),
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn grip_json_object_has_value(
+    amx: *const c_void,
+    object: Cell,
+    name: *const c_char,
+    json_type_filter: Cell,
+    dot_notation: bool,
+) -> Cell {
+    catch_panic_ffi!(amx, {
+        let object = try_to_get_json_value!(amx, object);
+        if !object.is_object() {
+            unconditionally_log_error!(
+                amx,
+                ffi_error(format!("JSON Handle is not object. {:?}", object))
+            );
+        }
+
+        match object.index_selective_safe(try_and_log_ffi!(amx, str_from_ptr(name)), dot_notation) {
+            Ok(value) if json_type_filter == -1 || json_type(value) == json_type_filter => 1,
+            _ => 0,
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn grip_json_object_set_value(
+    amx: *const c_void,
+    object: Cell,
+    name: *const c_char,
+    value: Cell,
+    dot_notation: bool,
+) -> Cell {
+    catch_panic_ffi!(amx, {
+        try_to_set_json_object_value!(
+            amx,
+            object,
+            name,
+            try_to_get_json_value!(amx, value).clone(),
+            dot_notation
+        )
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn grip_json_object_set_string(
+    amx: *const c_void,
+    object: Cell,
+    name: *const c_char,
+    string: *const c_char,
+    dot_notation: bool,
+) -> Cell {
+    catch_panic_ffi!(amx, {
+        try_to_set_json_object_value!(
+            amx,
+            object,
+            name,
+            json!(try_and_log_ffi!(
+                amx,
+                CStr::from_ptr(string)
+                    .to_str()
+                    .chain_err(|| ffi_error("Invalid string. Can't create UTF-8 string"))
+            )
+            .to_owned()),
+            dot_notation
+        )
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn grip_json_object_set_number(
+    amx: *const c_void,
+    object: Cell,
+    name: *const c_char,
+    number: Cell,
+    dot_notation: bool,
+) -> Cell {
+    catch_panic_ffi!(amx, {
+        try_to_set_json_object_value!(amx, object, name, json!(number), dot_notation)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn grip_json_object_set_float(
+    amx: *const c_void,
+    object: Cell,
+    name: *const c_char,
+    number: f32,
+    dot_notation: bool,
+) -> Cell {
+    catch_panic_ffi!(amx, {
+        try_to_set_json_object_value!(amx, object, name, json!(number), dot_notation)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn grip_json_object_set_bool(
+    amx: *const c_void,
+    object: Cell,
+    name: *const c_char,
+    boolean: bool,
+    dot_notation: bool,
+) -> Cell {
+    catch_panic_ffi!(amx, {
+        try_to_set_json_object_value!(amx, object, name, json!(boolean), dot_notation)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn grip_json_object_set_null(
+    amx: *const c_void,
+    object: Cell,
+    name: *const c_char,
+    dot_notation: bool,
+) -> Cell {
+    catch_panic_ffi!(amx, {
+        try_to_set_json_object_value!(amx, object, name, json!(null), dot_notation)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn grip_json_object_remove(
+    amx: *const c_void,
+    object: Cell,
+    name: *const c_char,
+    dot_notation: bool,
+) -> Cell {
+    catch_panic_ffi!(amx, {
+        try_and_log_ffi!(
+            amx,
+            try_to_get_json_value_mut!(amx, object)
+                .remove_selective(try_and_log_ffi!(amx, str_from_ptr(name)), dot_notation)
+        );
+
+        1
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn grip_json_object_clear(amx: *const c_void, object: Cell) -> Cell {
+    catch_panic_ffi!(amx, {
+        match try_to_get_json_value_mut!(amx, object) {
+            Value::Object(m) => {
+                m.clear();
+                1
+            }
+            v => unconditionally_log_error!(
+                amx,
+                ffi_error(format!("JSON Handle is not object. {:?}", v))
+            ),
+        }
+    })
 }
 
 #[no_mangle]
-pub unsafe extern "C" fn grip_json_array_get_value(
+pub unsafe extern "C" fn grip_json_pointer_get_value(
     amx: *const c_void,
-    array: Cell,
-    index: Cell,
+    value: Cell,
+    pointer: *const c_char,
 ) -> Cell {
-    match try_to_get_json_value!(amx, array) {
-        Value::Array(vec) => get_module_mut()
-            .json_handles
-            .insert_with_unique_id(vec[try_as_usize!(amx, index)].clone()),
-        v => {
-            unconditionally_log_error!(amx, ffi_error(format!("JSON Handle is not array. {:?}", v)))
-        }
-    }
+    catch_panic_ffi!(amx, {
+        try_and_log_ffi!(
+            amx,
+            get_module_mut().json_handles.insert_with_unique_id(
+                amx,
+                try_to_get_json_pointer_value!(amx, value, pointer).clone(),
+            )
+        )
+    })
 }
 
 #[no_mangle]
-pub unsafe extern "C" fn grip_json_array_get_string(
+pub unsafe extern "C" fn grip_json_pointer_get_string(
     amx: *const c_void,
-    array: Cell,
-    index: Cell,
+    value: Cell,
+    pointer: *const c_char,
     buffer: *mut c_char,
-    buffer_size: Cell,
+    maxlen: Cell,
 ) -> Cell {
-    match try_to_get_json_value!(amx, array) {
-        Value::Array(vec) => match &vec[try_as_usize!(amx, index)] {
-            Value::String(s) => copy_unsafe_string!(amx, buffer, s, buffer_size),
+    catch_panic_ffi!(amx, {
+        match try_to_get_json_pointer_value!(amx, value, pointer) {
+            Value::String(s) => copy_unsafe_string!(amx, buffer, s, maxlen),
             v => unconditionally_log_error!(
                 amx,
                 ffi_error(format!("JSON Handle is not string. {:?}", v))
             ),
-        },
-        v => {
-            unconditionally_log_error!(amx, ffi_error(format!("JSON Handle is not array. {:?}", v)))
         }
-    }
+    })
 }
 
 #[no_mangle]
-pub unsafe extern "C" fn grip_json_array_get_number(
+pub unsafe extern "C" fn grip_json_pointer_get_number(
     amx: *const c_void,
-    array: Cell,
-    index: Cell,
+    value: Cell,
+    pointer: *const c_char,
 ) -> Cell {
-    match try_to_get_json_value!(amx, array) {
-        Value::Array(vec) => match &vec[try_as_usize!(amx, index)] {
+    catch_panic_ffi!(amx, {
+        match try_to_get_json_pointer_value!(amx, value, pointer) {
             Value::Number(n) => try_and_log_ffi!(
                 amx,
                 n.as_i64().chain_err(|| ffi_error("Number is not integer"))
@@ -809,27 +2765,23 @@ pub unsafe extern "C" fn grip_json_array_get_number(
                 amx,
                 ffi_error(format!("JSON Handle is not number. {:?}", v))
             ),
-        },
-        v => {
-            unconditionally_log_error!(amx, ffi_error(format!("JSON Handle is not array. {:?}", v)))
         }
-    }
+    })
 }
 
 #[no_mangle]
-pub unsafe extern "C" fn grip_json_array_get_float(
+pub unsafe extern "C" fn grip_json_pointer_get_float(
     amx: *const c_void,
-    array: Cell,
-    index: Cell,
+    value: Cell,
+    pointer: *const c_char,
     ret: *mut f32,
 ) -> Cell {
-    match try_to_get_json_value!(amx, array) {
-        Value::Array(vec) => match &vec[try_as_usize!(amx, index)] {
+    catch_panic_ffi!(amx, {
+        match try_to_get_json_pointer_value!(amx, value, pointer) {
             Value::Number(n) => {
                 *ret = try_and_log_ffi!(
                     amx,
-                    n.as_f64()
-                        .chain_err(|| ffi_error("Number is not 32 bit float"))
+                    n.as_f64().chain_err(|| ffi_error("Number is not float"))
                 ) as f32;
 
                 1
@@ -838,384 +2790,369 @@ pub unsafe extern "C" fn grip_json_array_get_float(
                 amx,
                 ffi_error(format!("JSON Handle is not number. {:?}", v))
             ),
-        },
-        v => {
-            unconditionally_log_error!(amx, ffi_error(format!("JSON Handle is not array. {:?}", v)))
         }
-    }
+    })
 }
 
 #[no_mangle]
-pub unsafe extern "C" fn grip_json_array_get_bool(
+pub unsafe extern "C" fn grip_json_pointer_get_bool(
     amx: *const c_void,
-    array: Cell,
-    index: Cell,
+    value: Cell,
+    pointer: *const c_char,
 ) -> Cell {
-    match try_to_get_json_value!(amx, array) {
-        Value::Array(vec) => match &vec[try_as_usize!(amx, index)] {
+    catch_panic_ffi!(amx, {
+        match try_to_get_json_pointer_value!(amx, value, pointer) {
             Value::Bool(b) => *b as Cell,
             v => unconditionally_log_error!(
                 amx,
-                ffi_error(format!("JSON Handle is not number. {:?}", v))
+                ffi_error(format!("JSON Handle is not bool. {:?}", v))
             ),
-        },
-        v => {
-            unconditionally_log_error!(amx, ffi_error(format!("JSON Handle is not array. {:?}", v)))
-        }
-    }
-}
-
-#[no_mangle]
-pub unsafe extern "C" fn grip_json_array_get_count(amx: *const c_void, array: Cell) -> Cell {
-    match try_to_get_json_value!(amx, array) {
-        Value::Array(vec) => vec.len() as Cell,
-        v => {
-            unconditionally_log_error!(amx, ffi_error(format!("JSON Handle is not array. {:?}", v)))
         }
-    }
+    })
 }
 
 #[no_mangle]
-pub unsafe extern "C" fn grip_json_array_replace_value(
+pub unsafe extern "C" fn grip_json_pointer_set_value(
     amx: *const c_void,
-    array: Cell,
-    index: Cell,
+    target: Cell,
+    pointer: *const c_char,
     value: Cell,
 ) -> Cell {
-    match try_to_get_json_value_mut!(amx, array) {
-        Value::Array(vec) => {
-            vec[try_as_usize!(amx, index)] = try_to_get_json_value!(amx, value).clone();
-            1
-        }
-        v => {
-            unconditionally_log_error!(amx, ffi_error(format!("JSON Handle is not array. {:?}", v)))
-        }
-    }
+    catch_panic_ffi!(amx, {
+        try_to_set_json_pointer_value!(
+            amx,
+            target,
+            pointer,
+            try_to_get_json_value!(amx, value).clone()
+        )
+    })
 }
 
 #[no_mangle]
-pub unsafe extern "C" fn grip_json_array_replace_string(
+pub unsafe extern "C" fn grip_json_pointer_set_string(
     amx: *const c_void,
-    array: Cell,
-    index: Cell,
+    target: Cell,
+    pointer: *const c_char,
     string: *const c_char,
 ) -> Cell {
-    match try_to_get_json_value_mut!(amx, array) {
-        Value::Array(vec) => {
-            vec[try_as_usize!(amx, index)] = json!(try_and_log_ffi!(
+    catch_panic_ffi!(amx, {
+        try_to_set_json_pointer_value!(
+            amx,
+            target,
+            pointer,
+            json!(try_and_log_ffi!(
                 amx,
                 CStr::from_ptr(string)
                     .to_str()
                     .chain_err(|| ffi_error("Invalid string. Can't create UTF-8 string"))
             )
-            .to_owned());
-            1
-        }
-        v => {
-            unconditionally_log_error!(amx, ffi_error(format!("JSON Handle is not array. {:?}", v)))
-        }
-    }
+            .to_owned())
+        )
+    })
 }
 
 #[no_mangle]
-pub unsafe extern "C" fn grip_json_array_replace_number(
+pub unsafe extern "C" fn grip_json_pointer_set_number(
     amx: *const c_void,
-    array: Cell,
-    index: Cell,
-    value: Cell,
+    target: Cell,
+    pointer: *const c_char,
+    number: Cell,
 ) -> Cell {
-    match try_to_get_json_value_mut!(amx, array) {
-        Value::Array(vec) => {
-            vec[try_as_usize!(amx, index)] = json!(value);
-            1
-        }
-        v => {
-            unconditionally_log_error!(amx, ffi_error(format!("JSON Handle is not array. {:?}", v)))
-        }
-    }
+    catch_panic_ffi!(amx, {
+        try_to_set_json_pointer_value!(amx, target, pointer, json!(number))
+    })
 }
 
 #[no_mangle]
-pub unsafe extern "C" fn grip_json_array_replace_float(
+pub unsafe extern "C" fn grip_json_pointer_set_float(
     amx: *const c_void,
-    array: Cell,
-    index: Cell,
-    value: f32,
+    target: Cell,
+    pointer: *const c_char,
+    number: f32,
 ) -> Cell {
-    match try_to_get_json_value_mut!(amx, array) {
-        Value::Array(vec) => {
-            vec[try_as_usize!(amx, index)] = json!(value);
-            1
-        }
-        v => {
-            unconditionally_log_error!(amx, ffi_error(format!("JSON Handle is not array. {:?}", v)))
-        }
-    }
+    catch_panic_ffi!(amx, {
+        try_to_set_json_pointer_value!(amx, target, pointer, json!(number))
+    })
 }
 
 #[no_mangle]
-pub unsafe extern "C" fn grip_json_array_replace_bool(
+pub unsafe extern "C" fn grip_json_pointer_set_bool(
     amx: *const c_void,
-    array: Cell,
-    index: Cell,
-    value: bool,
+    target: Cell,
+    pointer: *const c_char,
+    boolean: bool,
 ) -> Cell {
-    match try_to_get_json_value_mut!(amx, array) {
-        Value::Array(vec) => {
-            vec[try_as_usize!(amx, index)] = json!(value);
-            1
-        }
-        v => {
-            unconditionally_log_error!(amx, ffi_error(format!("JSON Handle is not array. {:?}", v)))
-        }
-    }
+    catch_panic_ffi!(amx, {
+        try_to_set_json_pointer_value!(amx, target, pointer, json!(boolean))
+    })
 }
 
 #[no_mangle]
-pub unsafe extern "C" fn grip_json_array_replace_null(
+pub unsafe extern "C" fn grip_json_pointer_set_null(
     amx: *const c_void,
-    array: Cell,
-    index: Cell,
+    target: Cell,
+    pointer: *const c_char,
 ) -> Cell {
-    match try_to_get_json_value_mut!(amx, array) {
-        Value::Array(vec) => {
-            vec[try_as_usize!(amx, index)] = json!(null);
-            1
-        }
-        v => {
-            unconditionally_log_error!(amx, ffi_error(format!("JSON Handle is not array. {:?}", v)))
-        }
-    }
+    catch_panic_ffi!(amx, {
+        try_to_set_json_pointer_value!(amx, target, pointer, json!(null))
+    })
 }
 
-#[no_mangle]
-pub unsafe extern "C" fn grip_json_array_append_value(
-    amx: *const c_void,
-    array: Cell,
-    value: Cell,
-) -> Cell {
-    match try_to_get_json_value_mut!(amx, array) {
-        Value::Array(vec) => {
-            vec.push(try_to_get_json_value!(amx, value).clone());
-            1
-        }
-        v => {
-            unconditionally_log_error!(amx, ffi_error(format!("JSON Handle is not array. {:?}", v)))
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CString;
+    use std::sync::Mutex;
+
+    lazy_static! {
+        static ref ERRORS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+        static ref MESSAGES: Mutex<Vec<String>> = Mutex::new(Vec::new());
+        /// Module is global, so tests which use it can't run in parallel.
+        static ref MODULE_LOCK: Mutex<()> = Mutex::new(());
     }
-}
 
-#[no_mangle]
-pub unsafe extern "C" fn grip_json_array_append_string(
-    amx: *const c_void,
-    array: Cell,
-    string: *const c_char,
-) -> Cell {
-    match try_to_get_json_value_mut!(amx, array) {
-        Value::Array(vec) => {
-            vec.push(json!(try_and_log_ffi!(
-                amx,
-                CStr::from_ptr(string)
-                    .to_str()
-                    .chain_err(|| ffi_error("Invalid string. Can't create UTF-8 string"))
-            )
-            .to_owned()));
-            1
-        }
-        v => {
-            unconditionally_log_error!(amx, ffi_error(format!("JSON Handle is not array. {:?}", v)))
-        }
+    extern "C" fn error_logger(_amx: *const c_void, message: *const c_char) {
+        let message = unsafe { CStr::from_ptr(message) };
+        ERRORS
+            .lock()
+            .unwrap()
+            .push(message.to_string_lossy().into_owned());
     }
-}
 
-#[no_mangle]
-pub unsafe extern "C" fn grip_json_array_append_number(
-    amx: *const c_void,
-    array: Cell,
-    value: Cell,
-) -> Cell {
-    match try_to_get_json_value_mut!(amx, array) {
-        Value::Array(vec) => {
-            vec.push(json!(value));
-            1
-        }
-        v => {
-            unconditionally_log_error!(amx, ffi_error(format!("JSON Handle is not array. {:?}", v)))
-        }
+    extern "C" fn logger(message: *const c_char) {
+        let message = unsafe { CStr::from_ptr(message) };
+        MESSAGES
+            .lock()
+            .unwrap()
+            .push(message.to_string_lossy().into_owned());
     }
-}
 
-#[no_mangle]
-pub unsafe extern "C" fn grip_json_array_append_float(
-    amx: *const c_void,
-    array: Cell,
-    value: f32,
-) -> Cell {
-    match try_to_get_json_value_mut!(amx, array) {
-        Value::Array(vec) => {
-            vec.push(json!(value));
-            1
-        }
-        v => {
-            unconditionally_log_error!(amx, ffi_error(format!("JSON Handle is not array. {:?}", v)))
-        }
+    extern "C" fn plugin_name_getter(_amx: *const c_void) -> *const c_char {
+        std::ptr::null()
     }
-}
 
-#[no_mangle]
-pub unsafe extern "C" fn grip_json_array_append_bool(
-    amx: *const c_void,
-    array: Cell,
-    value: bool,
-) -> Cell {
-    match try_to_get_json_value_mut!(amx, array) {
-        Value::Array(vec) => {
-            vec.push(json!(value));
-            1
-        }
-        v => {
-            unconditionally_log_error!(amx, ffi_error(format!("JSON Handle is not array. {:?}", v)))
-        }
+    fn last_error() -> String {
+        ERRORS.lock().unwrap().pop().unwrap_or_default()
     }
-}
 
-#[no_mangle]
-pub unsafe extern "C" fn grip_json_array_append_null(amx: *const c_void, array: Cell) -> Cell {
-    match try_to_get_json_value_mut!(amx, array) {
-        Value::Array(vec) => {
-            vec.push(json!(null));
-            1
-        }
-        v => {
-            unconditionally_log_error!(amx, ffi_error(format!("JSON Handle is not array. {:?}", v)))
+    unsafe fn init_module(config_file_path: &str) {
+        MESSAGES.lock().unwrap().clear();
+        let path = CString::new(config_file_path).unwrap();
+        grip_init(error_logger, logger, plugin_name_getter, path.as_ptr());
+    }
+
+    #[test]
+    fn panics_are_caught() {
+        let _lock = MODULE_LOCK.lock().unwrap();
+        let plugin = 0u8;
+        let amx = &plugin as *const u8 as *const c_void;
+
+        unsafe {
+            // Module isn't initialized yet.
+            assert_eq!(grip_json_init_object(amx), 0);
+            grip_process_request();
+
+            init_module("missing-grip.ini");
+            assert_eq!(get_module().config, Config::default());
+            assert!(MESSAGES.lock().unwrap()[0].contains("doesn't exist"));
+
+            let array = grip_json_init_array(amx);
+            assert_eq!(grip_json_array_append_number(amx, array, 1), 1);
+
+            assert_eq!(grip_json_array_get_value(amx, array, 1), 0);
+            assert!(last_error().contains("out of bounds"));
+            assert_eq!(grip_json_array_get_number(amx, array, -1), 0);
+            assert!(last_error().contains("greater or equal to zero"));
+            assert_eq!(grip_json_array_replace_number(amx, array, 5, 2), 0);
+            assert!(last_error().contains("out of bounds"));
+            assert_eq!(grip_json_array_remove(amx, array, 1), 0);
+            assert!(last_error().contains("out of bounds"));
+            assert_eq!(grip_json_array_get_number(amx, array, 0), 1);
+
+            assert_eq!(catch_panic_ffi!(amx, { Vec::<Cell>::new()[0] }), 0);
+            assert!(last_error().starts_with("Internal gRIP error"));
+
+            grip_deinit();
+            assert_eq!(grip_json_get_type(amx, array), 0);
         }
     }
-}
+    #[test]
+    fn leaks_are_reported_when_grow() {
+        let _lock = MODULE_LOCK.lock().unwrap();
+        let plugin = 0u8;
+        let amx = &plugin as *const u8 as *const c_void;
+
+        unsafe {
+            for &(handles, reported) in &[(1, true), (1, false), (2, true), (1, false)] {
+                init_module("missing-grip.ini");
+                MESSAGES.lock().unwrap().clear();
+
+                for _ in 0..handles {
+                    grip_json_init_object(amx);
+                }
 
-#[no_mangle]
-pub unsafe extern "C" fn grip_json_array_remove(
-    amx: *const c_void,
-    array: Cell,
-    index: Cell,
-) -> Cell {
-    match try_to_get_json_value_mut!(amx, array) {
-        Value::Array(vec) => {
-            vec.remove(try_and_log_ffi!(
-                amx,
-                if index >= 0 {
-                    Ok(index as usize)
-                } else {
-                    Err(ffi_error(format!(
-                        "Index/Size {} should be greater or equal to zero.",
-                        index
-                    )))
+                grip_plugins_unloading();
+                assert_eq!(live_handles().len(), 0);
+
+                let messages = MESSAGES.lock().unwrap().clone();
+                assert_eq!(messages.len(), reported as usize);
+                if reported {
+                    assert!(messages[0].contains(&format!("left {} live GripJSONValue", handles)));
                 }
-            ));
-            1
-        }
-        v => {
-            unconditionally_log_error!(amx, ffi_error(format!("JSON Handle is not array. {:?}", v)))
+
+                grip_deinit();
+            }
         }
     }
-}
 
-#[no_mangle]
-pub unsafe extern "C" fn grip_json_array_clear(amx: *const c_void, array: Cell) -> Cell {
-    match try_to_get_json_value_mut!(amx, array) {
-        Value::Array(vec) => {
-            vec.clear();
-            1
-        }
-        v => {
-            unconditionally_log_error!(amx, ffi_error(format!("JSON Handle is not array. {:?}", v)))
+    #[test]
+    fn response_handler_state_is_restored() {
+        let _lock = MODULE_LOCK.lock().unwrap();
+        let plugin = 0u8;
+        let amx = &plugin as *const u8 as *const c_void;
+
+        unsafe {
+            init_module("missing-grip.ini");
+
+            let mut handle = 0;
+            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                call_response_handler(
+                    amx,
+                    "test.amxx",
+                    -1,
+                    Err(ErrorKind::RequestCancelled.into()),
+                    ResponseInfo::default(),
+                    || {
+                        assert_eq!(grip_get_response_state(amx), 1);
+                        handle = grip_json_init_object(amx);
+                        panic!("handler failed");
+                    },
+                )
+            }));
+            assert!(result.is_err());
+
+            assert!(get_module().current_response.is_none());
+            assert_eq!(grip_get_response_state(amx), 0);
+            // Automatic scope is closed, so the new scope is the only one.
+            assert_eq!(grip_json_scope_begin(amx), 1);
+            assert_eq!(grip_json_scope_end(amx), 0);
+            assert_eq!(grip_json_get_type(amx, handle), 0);
+
+            grip_deinit();
         }
     }
-}
 
-#[no_mangle]
-pub unsafe extern "C" fn grip_json_object_get_value(
-    amx: *const c_void,
-    object: Cell,
-    name: *const c_char,
-    dot_notation: bool,
-) -> Cell {
-    get_module_mut().json_handles.insert_with_unique_id(
-        try_to_get_json_object_value!(amx, object, name, dot_notation).clone(),
-    )
-}
-#[no_mangle]
-pub unsafe extern "C" fn grip_json_object_get_string(
-    amx: *const c_void,
-    object: Cell,
-    name: *const c_char,
-    buffer: *mut c_char,
-    maxlen: Cell,
-    dot_notation: bool,
-) -> Cell {
-    match try_to_get_json_object_value!(amx, object, name, dot_notation) {
-        Value::String(s) => copy_unsafe_string!(amx, buffer, s, maxlen),
-        v => unconditionally_log_error!(
-            amx,
-            ffi_error(format!("JSON Handle is not string. {:?}", v))
-        ),
-    }
-}
+    #[test]
+    fn log_records_are_forwarded() {
+        let _lock = MODULE_LOCK.lock().unwrap();
 
-#[no_mangle]
-pub unsafe extern "C" fn grip_json_object_get_number(
-    amx: *const c_void,
-    object: Cell,
-    name: *const c_char,
-    dot_notation: bool,
-) -> Cell {
-    match try_to_get_json_object_value!(amx, object, name, dot_notation) {
-        Value::Number(n) => try_and_log_ffi!(
-            amx,
-            n.as_i64().chain_err(|| ffi_error("Number is not integer"))
-        ) as Cell,
-        v => unconditionally_log_error!(
-            amx,
-            ffi_error(format!("JSON Handle is not number. {:?}", v))
-        ),
+        unsafe {
+            init_module("missing-grip.ini");
+            MESSAGES.lock().unwrap().clear();
+
+            warn!("main thread");
+            info!("below the level");
+            std::thread::spawn(|| warn!("worker thread"))
+                .join()
+                .unwrap();
+
+            assert_eq!(
+                *MESSAGES.lock().unwrap(),
+                vec!["[gRIP] Warning: main thread"]
+            );
+
+            // Records of the worker threads are written on the main thread.
+            logger::flush();
+            assert_eq!(
+                *MESSAGES.lock().unwrap(),
+                vec![
+                    "[gRIP] Warning: main thread",
+                    "[gRIP] Warning: worker thread"
+                ]
+            );
+
+            grip_deinit();
+        }
     }
-}
 
-#[no_mangle]
-pub unsafe extern "C" fn grip_json_object_get_float(
-    amx: *const c_void,
-    object: Cell,
-    name: *const c_char,
-    dot_notation: bool,
-    ret: *mut f32,
-) -> Cell {
-    match try_to_get_json_object_value!(amx, object, name, dot_notation) {
-        Value::Number(n) => {
-            *ret = try_and_log_ffi!(
-                amx,
-                n.as_f64().chain_err(|| ffi_error("Number is not float"))
-            ) as f32;
+    #[test]
+    fn serial_to_file_keeps_file_on_failure() {
+        let _lock = MODULE_LOCK.lock().unwrap();
+        let plugin = 0u8;
+        let amx = &plugin as *const u8 as *const c_void;
+        let path = std::env::temp_dir().join("grip-serial-test.json");
+        let path_string = CString::new(path.to_str().unwrap()).unwrap();
+        std::fs::write(&path, "old").unwrap();
 
-            1
+        unsafe {
+            init_module("missing-grip.ini");
+
+            assert_eq!(
+                grip_json_serial_to_file(amx, -1, path_string.as_ptr(), false),
+                0
+            );
+            assert_eq!(std::fs::read_to_string(&path).unwrap(), "old");
+
+            let object = grip_json_init_object(amx);
+            assert_eq!(
+                grip_json_serial_to_file(amx, object, path_string.as_ptr(), false),
+                1
+            );
+            assert_eq!(std::fs::read_to_string(&path).unwrap(), "{}");
+            assert!(!path.with_extension("json.tmp").exists());
+
+            // Rename over the directory fails, so it stays as is.
+            let directory = std::env::temp_dir().join("grip-serial-test-directory");
+            std::fs::create_dir_all(&directory).unwrap();
+            let directory_string = CString::new(directory.to_str().unwrap()).unwrap();
+            assert_eq!(
+                grip_json_serial_to_file(amx, object, directory_string.as_ptr(), false),
+                0
+            );
+            assert!(last_error().contains("Can't write to the file"));
+            assert!(directory.is_dir());
+            assert!(!directory.with_extension("tmp").exists());
+
+            grip_deinit();
         }
-        v => unconditionally_log_error!(
-            amx,
-            ffi_error(format!("JSON Handle is not number. {:?}", v))
-        ),
+
+        std::fs::remove_file(&path).unwrap();
     }
-}
 
-#[no_mangle]
-pub unsafe extern "C" fn grip_json_object_get_bool(
-    amx: *const c_void,
-    object: Cell,
-    name: *const c_char,
-    dot_notation: bool,
-) -> Cell {
-    match try_to_get_json_object_value!(amx, object, name, dot_notation) {
-        Value::Bool(b) => *b as Cell,
-        v => unconditionally_log_error!(
-            amx,
-            ffi_error(format!("JSON Handle is not bool. {:?}", v))
-        ),
+    #[test]
+    fn reload_config() {
+        let _lock = MODULE_LOCK.lock().unwrap();
+        let path = std::env::temp_dir().join("grip-reload-test.ini");
+        let path = path.to_str().unwrap();
+        std::fs::write(path, "[queue]\ncallbacks-per-frame = 4\n").unwrap();
+
+        unsafe {
+            init_module(path);
+            assert_eq!(get_module().config.callbacks_per_frame, 4);
+            assert!(MESSAGES.lock().unwrap().is_empty());
+
+            std::fs::write(
+                path,
+                "[queue]\ncallbacks-per-frame = 2\nunknown = 1\n\
+                 [network]\nmax-concurrent-requests = 8\n",
+            )
+            .unwrap();
+
+            let mut report = [0 as c_char; 512];
+            let buffer = report.as_mut_ptr();
+            assert_eq!(grip_reload_config(std::ptr::null(), buffer, 511), 2);
+            assert_eq!(get_module().config.callbacks_per_frame, 2);
+            // Network settings are applied at runtime as well.
+            assert_eq!(get_module().config.max_concurrent_requests, 8);
+
+            let report = CStr::from_ptr(buffer).to_str().unwrap();
+            assert!(report.contains("Warning at line 3"));
+            assert!(report.contains("queue.callbacks-per-frame: 4 -> 2"));
+            assert!(report.contains("network.max-concurrent-requests: 0 -> 8"));
+            assert!(!report.contains("restart"));
+            assert_eq!(MESSAGES.lock().unwrap().len(), 1);
+
+            grip_deinit();
+        }
     }
 }
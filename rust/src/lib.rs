@@ -57,6 +57,9 @@ mod errors {
             RequestTimeout {
                 display("Request timeout")
             }
+            RequestRateLimited {
+                display("Request was rate limited, waiting for the token of its group would exceed the timeout")
+            }
         }
 
         foreign_links {
@@ -72,5 +75,8 @@ mod errors {
 }
 
 pub mod cell_map;
+pub mod concurrency_limits;
+pub mod config;
 pub mod ffi;
 pub mod networking_queue;
+pub mod rate_limits;
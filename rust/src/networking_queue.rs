@@ -34,10 +34,15 @@ use std::thread;
 use futures::future;
 use futures::prelude::*;
 use futures::sync::oneshot;
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::mem;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
+use crate::concurrency_limits::ConcurrencyLimits;
 use crate::errors::*;
+use crate::rate_limits::{host_key, RateLimitGroup, RateLimits, TokenBuckets};
 
 use tokio::prelude::FutureExt;
 
@@ -45,17 +50,131 @@ use reqwest::r#async as reqwest_async;
 
 use clone_all::clone_all;
 
+/// Used as the timeout of requests which have none, about 18 hours. It bounds every attempt and
+/// the wait for the group token, so a hung connection or a stalled group frees its request
+/// eventually. Host holds (`Retry-After` and rate-limit headers) are bounded by
+/// `RateLimits::max_hold` instead, and unparsable `Retry-After` values are ignored.
+const MAX_TIMEOUT: Duration = Duration::from_secs(u16::MAX as u64);
+
 #[derive(Clone, Debug)]
 pub enum RequestType {
     Get,
     Post,
     Put,
     Delete,
+    Patch,
+    Head,
+    Options,
+    Custom(String),
+}
+
+impl RequestType {
+    /// Converts request type to the HTTP method. Custom verbs are validated here.
+    pub fn to_method(&self) -> Result<reqwest::Method> {
+        Ok(match self {
+            RequestType::Get => reqwest::Method::GET,
+            RequestType::Post => reqwest::Method::POST,
+            RequestType::Put => reqwest::Method::PUT,
+            RequestType::Delete => reqwest::Method::DELETE,
+            RequestType::Patch => reqwest::Method::PATCH,
+            RequestType::Head => reqwest::Method::HEAD,
+            RequestType::Options => reqwest::Method::OPTIONS,
+            RequestType::Custom(verb) => reqwest::Method::from_bytes(verb.as_bytes())
+                .chain_err(|| format!("Invalid HTTP method `{}`", verb))?,
+        })
+    }
 }
 
 #[derive(Debug)]
 pub struct RequestCancellation(oneshot::Sender<()>);
 
+/// Failures which are retried, see `RetryPolicy`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RetryConditions {
+    /// Request wasn't sent or response wasn't received: connection refused/reset, DNS errors, etc.
+    pub connection_errors: bool,
+    pub timeouts: bool,
+    /// 5xx status codes.
+    pub server_errors: bool,
+    /// 429 status code.
+    pub too_many_requests: bool,
+}
+
+impl Default for RetryConditions {
+    fn default() -> Self {
+        RetryConditions {
+            connection_errors: true,
+            timeouts: true,
+            server_errors: true,
+            too_many_requests: true,
+        }
+    }
+}
+
+/// Retries are executed by the queue worker, so callback is called only for the last attempt.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RetryPolicy {
+    /// Maximum number of attempts including the first one. One means no retries.
+    pub max_attempts: usize,
+    /// Delay before the first retry, doubled for every next one.
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    /// Part of the delay, which is randomized. From 0.0 (no jitter) to 1.0 (full jitter).
+    pub jitter: f64,
+    pub conditions: RetryConditions,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 1,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(30),
+            jitter: 0.5,
+            conditions: RetryConditions::default(),
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn should_retry_status(&self, status_code: reqwest::StatusCode) -> bool {
+        (self.conditions.server_errors && status_code.is_server_error())
+            || (self.conditions.too_many_requests
+                && status_code == reqwest::StatusCode::TOO_MANY_REQUESTS)
+    }
+
+    /// Delay after the attempt with the given number, starting from 1.
+    pub fn delay(&self, attempt_number: usize) -> Duration {
+        let exponent = std::cmp::min(attempt_number.saturating_sub(1), 31) as u32;
+        let delay = std::cmp::min(
+            self.base_delay
+                .checked_mul(1 << exponent)
+                .unwrap_or(self.max_delay),
+            self.max_delay,
+        );
+
+        let random = f64::from(rand::random::<u32>()) / f64::from(u32::MAX);
+        delay.mul_f64(1.0 - self.jitter * random)
+    }
+}
+
+/// Higher priority requests are sent first, when concurrency is limited,
+/// and their callbacks are executed before the callbacks of lower priority requests.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum RequestPriority {
+    Low,
+    Normal,
+    High,
+}
+
+// Deriving it with `#[default]` on the variant would require rustc 1.62.
+#[allow(clippy::derivable_impls)]
+impl Default for RequestPriority {
+    fn default() -> Self {
+        RequestPriority::Normal
+    }
+}
+
 #[derive(Constructor, Builder, Clone, Debug, Default)]
 pub struct RequestOptions {
     #[builder(default)]
@@ -63,6 +182,16 @@ pub struct RequestOptions {
 
     #[builder(default)]
     pub timeout: Option<Duration>,
+
+    #[builder(default)]
+    pub retry: RetryPolicy,
+
+    #[builder(default)]
+    pub priority: RequestPriority,
+
+    /// Name of the token bucket group, see `Queue::set_rate_limit_groups`.
+    #[builder(default)]
+    pub rate_limit_group: Option<String>,
 }
 
 #[derive(Builder, Clone, Constructor, Debug)]
@@ -75,6 +204,10 @@ pub struct Request {
 
     #[builder(default)]
     pub options: RequestOptions,
+
+    /// Responses of different groups are dispatched fairly, see `Queue::set_group_weight`.
+    #[builder(default)]
+    pub group: usize,
 }
 
 #[derive(Constructor, Builder)]
@@ -82,11 +215,125 @@ pub struct Response {
     pub base_request: Request,
     pub body: Vec<u8>,
     pub status_code: reqwest::StatusCode,
+    pub headers: reqwest::header::HeaderMap,
+}
+
+/// Statistics of the single `Queue::execute_queue_with_limit` call.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ExecutionStats {
+    /// Number of executed callbacks.
+    pub executed: usize,
+    /// Time consumed by the callbacks.
+    pub elapsed: Duration,
+}
+
+/// Information about request execution, which is available for the errors as well.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ResponseInfo {
+    /// Number of the sent attempts, zero if request wasn't sent at all.
+    pub attempts: usize,
+    /// Time request was held, because host asked to slow down or waited for the token of its group.
+    pub held: Duration,
 }
 
 // TODO: Replace with trait alias, when they became stable
 // https://github.com/rust-lang/rust/issues/41517
-type ResponseCallBack = Fn(Result<Response>) + Sync + Send;
+type ResponseCallBack = Fn(Result<Response>, ResponseInfo) + Sync + Send;
+
+/// Requests wait in `ConcurrencyLimits` with the sender of their future slot.
+type SharedConcurrencyLimits = Arc<Mutex<ConcurrencyLimits<oneshot::Sender<Slot>>>>;
+
+/// Place of the request in flight, it's freed when dropped.
+struct Slot {
+    concurrency_limits: SharedConcurrencyLimits,
+    host: String,
+}
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        self.concurrency_limits.lock().unwrap().finish(&self.host);
+        grant_slots(&self.concurrency_limits);
+    }
+}
+
+/// Waits for the free slot. Request stops waiting, when the future is dropped,
+/// so cancelled requests free their place in the queue at once.
+struct SlotRequest {
+    concurrency_limits: SharedConcurrencyLimits,
+    id: usize,
+    receiver: oneshot::Receiver<Slot>,
+}
+
+impl SlotRequest {
+    fn new(
+        concurrency_limits: &SharedConcurrencyLimits,
+        host: String,
+        priority: RequestPriority,
+    ) -> Self {
+        let (sender, receiver) = oneshot::channel();
+        let id = concurrency_limits
+            .lock()
+            .unwrap()
+            .push(host, priority, sender);
+        grant_slots(concurrency_limits);
+
+        SlotRequest {
+            concurrency_limits: Arc::clone(concurrency_limits),
+            id,
+            receiver,
+        }
+    }
+}
+
+impl Future for SlotRequest {
+    type Item = Slot;
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<Slot, ()> {
+        self.receiver.poll().map_err(|_| ())
+    }
+}
+
+impl Drop for SlotRequest {
+    fn drop(&mut self) {
+        // Slot which was already granted is freed with the receiver.
+        self.concurrency_limits.lock().unwrap().remove(self.id);
+    }
+}
+
+/// Token of the rate limit group taken in advance. It's given back, unless request is sent.
+struct TokenReservation {
+    token_buckets: Arc<Mutex<TokenBuckets>>,
+    group: Option<String>,
+}
+
+impl TokenReservation {
+    fn consume(mut self) {
+        self.group = None;
+    }
+}
+
+impl Drop for TokenReservation {
+    fn drop(&mut self) {
+        if let Some(group) = self.group.take() {
+            self.token_buckets.lock().unwrap().release(&group);
+        }
+    }
+}
+
+/// Passes slots to the waiting requests, which fit into the limits.
+fn grant_slots(concurrency_limits: &SharedConcurrencyLimits) {
+    let ready = concurrency_limits.lock().unwrap().pop_ready();
+    for (host, sender) in ready {
+        // Slot is freed right away, if request has stopped waiting.
+        sender
+            .send(Slot {
+                concurrency_limits: Arc::clone(concurrency_limits),
+                host,
+            })
+            .ok();
+    }
+}
 
 #[allow(clippy::large_enum_variant)]
 enum InputCommand {
@@ -103,18 +350,71 @@ enum OutputCommand {
     Response {
         response: Response,
         callback: Box<ResponseCallBack>,
+        info: ResponseInfo,
     },
     Error {
         error: Error,
         callback: Box<ResponseCallBack>,
+        info: ResponseInfo,
     },
 }
 
+/// Ready responses of the single priority. Groups are served in weighted round-robin.
+#[derive(Default)]
+struct ReadyResponses {
+    responses: HashMap<usize, VecDeque<OutputCommand>>,
+    /// Groups with ready responses in the round-robin order, current group is the first one.
+    groups: VecDeque<usize>,
+    /// Responses left to execute in the turn of the current group.
+    turn_credit: usize,
+}
+
+impl ReadyResponses {
+    fn push(&mut self, group: usize, command: OutputCommand) {
+        let responses = self.responses.entry(group).or_default();
+        if responses.is_empty() {
+            self.groups.push_back(group);
+        }
+        responses.push_back(command);
+    }
+
+    fn pop(&mut self, group_weights: &HashMap<usize, usize>) -> Option<OutputCommand> {
+        let group = *self.groups.front()?;
+
+        if self.turn_credit == 0 {
+            self.turn_credit = group_weights.get(&group).cloned().unwrap_or(1);
+        }
+        self.turn_credit -= 1;
+
+        let responses = self.responses.get_mut(&group).unwrap();
+        let command = responses.pop_front();
+
+        if responses.is_empty() {
+            self.responses.remove(&group);
+            self.groups.pop_front();
+            self.turn_credit = 0;
+        } else if self.turn_credit == 0 {
+            self.groups.rotate_left(1);
+        }
+
+        command
+    }
+
+    fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+}
+
 pub struct Queue {
     working_thread: Option<thread::JoinHandle<()>>,
     executor: tokio::runtime::TaskExecutor,
     input_command_sender: futures::sync::mpsc::UnboundedSender<InputCommand>,
-    response_receiver: crossbeam_channel::Receiver<OutputCommand>,
+    response_receiver: crossbeam_channel::Receiver<(RequestPriority, usize, OutputCommand)>,
+    rate_limits: Arc<Mutex<RateLimits>>,
+    token_buckets: Arc<Mutex<TokenBuckets>>,
+    concurrency_limits: SharedConcurrencyLimits,
+    ready_responses: BTreeMap<RequestPriority, ReadyResponses>,
+    group_weights: HashMap<usize, usize>,
     last_time_executed_with_limit: Option<Instant>,
     number_of_pending_requests: usize,
 }
@@ -140,15 +440,28 @@ impl Queue {
         let (response_sender, response_receiver) = crossbeam_channel::unbounded();
 
         let client = reqwest_async::Client::new();
+        let rate_limits = Arc::new(Mutex::new(RateLimits::default()));
+        let token_buckets = Arc::new(Mutex::new(TokenBuckets::default()));
+        let concurrency_limits = Arc::new(Mutex::new(ConcurrencyLimits::default()));
 
         let working_thread = {
             let executor = executor.clone();
-            clone_all!(response_sender);
+            clone_all!(
+                response_sender,
+                rate_limits,
+                token_buckets,
+                concurrency_limits
+            );
             thread::spawn(move || {
-                clone_all!(response_sender);
+                clone_all!(
+                    response_sender,
+                    rate_limits,
+                    token_buckets,
+                    concurrency_limits
+                );
                 runtime
                     .block_on(future::lazy(move || {
-                        clone_all!(response_sender);
+                        clone_all!(response_sender, rate_limits, token_buckets, concurrency_limits);
                         input_command_receiver
                             .take_while(|cmd| {
                                 Ok(match cmd {
@@ -159,89 +472,189 @@ impl Queue {
                                     _ => true,
                                 })
                             }).for_each(move |cmd| {
-                                clone_all!(response_sender);
+                                clone_all!(response_sender, rate_limits, token_buckets, concurrency_limits);
                                 match cmd {
                                     InputCommand::Quit => unreachable!(),
                                     InputCommand::Request { request, callback, cancellation_signal } => {
 
                                         enum State {
-                                            Successful(Vec<u8>, reqwest::StatusCode),
+                                            Successful(Vec<u8>, reqwest::StatusCode, reqwest::header::HeaderMap),
                                             Error(Error),
                                             Canceled,
-                                            Timeout
+                                            Timeout,
+                                            RateLimited
                                         }
 
 
-                                        executor.spawn(
-                                            // Request construction.
-                                            match request.http_type {
-                                                RequestType::Post => client.post(request.uri.clone()),
-                                                RequestType::Get => client.get(request.uri.clone()),
-                                                RequestType::Delete => client.delete(request.uri.clone()),
-                                                RequestType::Put => client.put(request.uri.clone()),
+                                        let group = request.group;
+                                        let priority = request.options.priority;
+                                        let method = match request.http_type.to_method() {
+                                            Ok(method) => method,
+                                            Err(error) => {
+                                                response_sender.send((priority, group, OutputCommand::Error {
+                                                    error,
+                                                    callback,
+                                                    info: ResponseInfo::default(),
+                                                })).unwrap();
+                                                return Ok(());
                                             }
-                                                .body(reqwest_async::Body::from(request.body.clone()))
-                                                .headers(request.options.headers.clone()) // TODO: Optimize clone away
-                                                .send()
-                                                .and_then(move |res| {
-                                                    let status = res.status();
-                                                    res.into_body().concat2().map(move |body| (status, body))
-                                                })
-                                                // Cancelling / Error handling.
-                                                .map(|(status_code, body)| {
-                                                    State::Successful(body.to_vec(), status_code)
-                                                })
-                                                .or_else(|e| {
-                                                    future::ok(State::Error(ErrorKind::HTTPError(e).into()))
-                                                })
-                                                .select2(cancellation_signal
-                                                    .map(|_| State::Canceled)
-                                                    .or_else(|_| future::ok(State::Canceled))
-                                                )
+                                        };
+
+                                        let timeout = request.options.timeout
+                                            .unwrap_or(MAX_TIMEOUT);
+                                        let attempts = Arc::new(AtomicUsize::new(0));
+                                        let held_microseconds = Arc::new(AtomicUsize::new(0));
+                                        let host = host_key(&request.uri);
+
+                                        // Single attempt, timeout is applied to every attempt separately.
+                                        // Attempt is held while host is rate limited and until token of its group is available.
+                                        let attempt = {
+                                            clone_all!(client, request, attempts, held_microseconds, rate_limits, token_buckets, host);
+                                            move || {
+                                                let token_wait = match &request.options.rate_limit_group {
+                                                    Some(group) => token_buckets.lock().unwrap().acquire(group, Instant::now(), timeout),
+                                                    None => Some(Duration::new(0, 0)),
+                                                };
+                                                let token_wait = match token_wait {
+                                                    Some(token_wait) => token_wait,
+                                                    None => return future::Either::A(future::ok(State::RateLimited)),
+                                                };
+                                                // Cancelled request gives the token back.
+                                                let reservation = TokenReservation {
+                                                    token_buckets: Arc::clone(&token_buckets),
+                                                    group: request.options.rate_limit_group.clone(),
+                                                };
+
+                                                let hold = std::cmp::max(
+                                                    rate_limits.lock().unwrap().hold_time(&host, Instant::now()),
+                                                    token_wait
+                                                );
+                                                held_microseconds.fetch_add(hold.as_micros() as usize, Ordering::SeqCst);
+
+                                                clone_all!(client, request, attempts, rate_limits, host, method);
+                                                future::Either::B(tokio::timer::Delay::new(Instant::now() + hold).then(move |_| {
+                                                    reservation.consume();
+                                                    attempts.fetch_add(1, Ordering::SeqCst);
+                                                    client.request(method, request.uri.clone())
+                                                        .body(reqwest_async::Body::from(request.body.clone()))
+                                                        .headers(request.options.headers.clone()) // TODO: Optimize clone away
+                                                        .send()
+                                                        .and_then(move |mut res| {
+                                                            let status = res.status();
+                                                            let headers = mem::take(res.headers_mut());
+                                                            rate_limits.lock().unwrap().update(
+                                                                &host,
+                                                                status,
+                                                                &headers,
+                                                                Instant::now(),
+                                                                time::get_time().sec
+                                                            );
+                                                            res.into_body().concat2().map(move |body| (status, headers, body))
+                                                        })
+                                                        .map(|(status_code, headers, body)| {
+                                                            State::Successful(body.to_vec(), status_code, headers)
+                                                        })
+                                                        .or_else(|e| {
+                                                            future::ok::<_, ()>(State::Error(ErrorKind::HTTPError(e).into()))
+                                                        })
+                                                        .timeout(timeout)
+                                                        .or_else(|_| future::ok::<_, ()>(State::Timeout))
+                                                }))
+                                            }
+                                        };
+
+                                        // Retrying. Lazy, so the first attempt isn't prepared before the job is started.
+                                        let retry = request.options.retry.clone();
+                                        let attempts_loop = future::lazy(move || future::loop_fn(1, move |attempt_number| {
+                                            let retry = retry.clone();
+                                            attempt().and_then(move |state| {
+                                                let should_retry = match &state {
+                                                    State::Successful(_, status_code, _) => retry.should_retry_status(*status_code),
+                                                    State::Error(_) => retry.conditions.connection_errors,
+                                                    State::Timeout => retry.conditions.timeouts,
+                                                    State::Canceled | State::RateLimited => false,
+                                                };
+
+                                                if should_retry && attempt_number < retry.max_attempts {
+                                                    future::Either::A(
+                                                        tokio::timer::Delay::new(Instant::now() + retry.delay(attempt_number))
+                                                            .then(move |_| Ok(future::Loop::Continue(attempt_number + 1)))
+                                                    )
+                                                } else {
+                                                    future::Either::B(future::ok(future::Loop::Break(state)))
+                                                }
+                                            })
+                                        }));
+
+                                        // Request waits for the slot, while concurrency is limited.
+                                        let job = SlotRequest::new(&concurrency_limits, host, priority)
+                                            .and_then(move |slot| attempts_loop.map(move |state| {
+                                                drop(slot);
+                                                state
+                                            }));
+
+                                        executor.spawn(
+                                            // Cancelling. Signal is polled first, so cancelled request doesn't start new attempt.
+                                            cancellation_signal
+                                                .map(|_| State::Canceled)
+                                                .or_else(|_| future::ok(State::Canceled))
+                                                .select2(job)
                                                 .map_err(|_: future::Either<((), _), ((), _)>| unreachable!())
                                                 .map(|either| {
                                                     either.split().0
                                                 })
-                                                // Timeout.
-                                                .timeout(request.options.timeout
-                                                    .unwrap_or_else(|| Duration::new(u64::from(std::u16::MAX), 0)))
-                                                .or_else(|_| future::ok(State::Timeout))
-                                                .map_err(|_:tokio::timer::Error| unreachable!())
                                                 // Sending output command.
                                                 .and_then(move |state| {
+                                                    let info = ResponseInfo {
+                                                        attempts: attempts.load(Ordering::SeqCst),
+                                                        held: Duration::from_micros(held_microseconds.load(Ordering::SeqCst) as u64),
+                                                    };
+
                                                     match state {
-                                                        State::Successful(vec, status_code) => {
-                                                            response_sender.send(OutputCommand::Response {
+                                                        State::Successful(vec, status_code, headers) => {
+                                                            response_sender.send((priority, group, OutputCommand::Response {
                                                                 response: Response::new(
                                                                     request,
                                                                     vec,
-                                                                    status_code
+                                                                    status_code,
+                                                                    headers
                                                                 ),
-                                                                callback
-                                                            }).unwrap()
+                                                                callback,
+                                                                info,
+                                                            })).unwrap()
                                                         },
                                                         State::Error(error) => {
-                                                            response_sender.send(OutputCommand::Error {
+                                                            response_sender.send((priority, group, OutputCommand::Error {
                                                                 error,
                                                                 callback,
-                                                            }).unwrap();
+                                                                info,
+                                                            })).unwrap();
                                                         },
                                                         State::Canceled => {
-                                                            response_sender.send(OutputCommand::Error {
+                                                            response_sender.send((priority, group, OutputCommand::Error {
                                                                 error: ErrorKind::RequestCancelled.into(),
                                                                 callback,
-                                                            }).unwrap();
+                                                                info,
+                                                            })).unwrap();
                                                         }
                                                         State::Timeout => {
-                                                            response_sender.send(OutputCommand::Error {
+                                                            response_sender.send((priority, group, OutputCommand::Error {
                                                                 error: ErrorKind::RequestTimeout.into(),
                                                                 callback,
-                                                            }).unwrap()
+                                                                info,
+                                                            })).unwrap()
+                                                        }
+                                                        State::RateLimited => {
+                                                            response_sender.send((priority, group, OutputCommand::Error {
+                                                                error: ErrorKind::RequestRateLimited.into(),
+                                                                callback,
+                                                                info,
+                                                            })).unwrap()
                                                         }
                                                     }
                                                     future::ok(())
                                                 }).map(|_| {})
-                                        )
+                                        );
                                     }
                                 }
 
@@ -256,6 +669,11 @@ impl Queue {
             executor,
             input_command_sender,
             response_receiver,
+            rate_limits,
+            token_buckets,
+            concurrency_limits,
+            ready_responses: BTreeMap::new(),
+            group_weights: HashMap::new(),
             last_time_executed_with_limit: None,
             number_of_pending_requests: 0,
         }
@@ -270,7 +688,7 @@ impl Queue {
     }
 
     #[must_use = "this `RequestCancellation` should be alive, because when it drops request cancels."]
-    pub fn send_request<T: 'static + Fn(Result<Response>) + Sync + Send>(
+    pub fn send_request<T: 'static + Fn(Result<Response>, ResponseInfo) + Sync + Send>(
         &mut self,
         request: Request,
         callback: T,
@@ -297,42 +715,114 @@ impl Queue {
         }));
     }
 
+    /// Sets maximum time requests are held, when host asks to slow down.
+    pub fn set_max_rate_limit_hold(&mut self, max_hold: Duration) {
+        self.rate_limits.lock().unwrap().max_hold = max_hold;
+    }
+
+    /// Sets token bucket groups, which requests can be assigned to using `RequestOptions::rate_limit_group`.
+    /// Requests wait for the token of their group, unless waiting would exceed their timeout.
+    pub fn set_rate_limit_groups(&mut self, groups: &HashMap<String, RateLimitGroup>) {
+        self.token_buckets
+            .lock()
+            .unwrap()
+            .set_groups(groups, Instant::now());
+    }
+
+    /// Sets maximum number of the requests in flight, overall and per host. Zero means unlimited.
+    /// Requests above the limits wait in FIFO order of their priority.
+    /// Retries and rate limit holds occupy the slot.
+    pub fn set_concurrency_limits(&mut self, max_in_flight: usize, max_in_flight_per_host: usize) {
+        {
+            let mut concurrency_limits = self.concurrency_limits.lock().unwrap();
+            concurrency_limits.max_in_flight = max_in_flight;
+            concurrency_limits.max_in_flight_per_host = max_in_flight_per_host;
+        }
+
+        grant_slots(&self.concurrency_limits);
+    }
+
+    /// Sets how many responses of the group are executed in its turn. Default weight is 1.
+    pub fn set_group_weight(&mut self, group: usize, weight: usize) {
+        self.group_weights.insert(group, std::cmp::max(weight, 1));
+    }
+
     fn try_recv_queue(&mut self) -> Result<()> {
-        match self.response_receiver.try_recv()? {
-            OutputCommand::Response { response, callback } => {
-                (callback)(Ok(response));
-            }
-            OutputCommand::Error { error, callback } => {
-                (callback)(Err(error));
-            }
+        for (priority, group, command) in self.response_receiver.try_iter() {
+            self.ready_responses
+                .entry(priority)
+                .or_default()
+                .push(group, command);
         }
 
+        // State is updated before callback, because it can send new requests.
+        let (&priority, ready_responses) = self
+            .ready_responses
+            .iter_mut()
+            .next_back()
+            .chain_err(|| "There are no ready responses")?;
+
+        let command = ready_responses.pop(&self.group_weights).unwrap();
+        if ready_responses.is_empty() {
+            self.ready_responses.remove(&priority);
+        }
         self.number_of_pending_requests -= 1;
 
+        match command {
+            OutputCommand::Response {
+                response,
+                callback,
+                info,
+            } => {
+                (callback)(Ok(response), info);
+            }
+            OutputCommand::Error {
+                error,
+                callback,
+                info,
+            } => {
+                (callback)(Err(error), info);
+            }
+        }
+
         Ok(())
     }
 
+    /// Executes at most `limit` callbacks, stops earlier if callbacks exhausted the time `budget`.
+    /// Returns `None` if previous execution was less than `delay_between_executions` ago.
     pub fn execute_queue_with_limit(
         &mut self,
         limit: usize,
+        budget: Option<Duration>,
         delay_between_executions: Duration,
-    ) -> usize {
+    ) -> Option<ExecutionStats> {
         if let Some(last_time) = self.last_time_executed_with_limit {
             if Instant::now().duration_since(last_time) <= delay_between_executions {
-                return 0;
+                return None;
             }
         }
 
         self.last_time_executed_with_limit = Some(Instant::now());
 
-        let mut counter = 0;
-        while counter <= limit {
+        let started = Instant::now();
+        let mut executed = 0;
+        while executed < limit {
+            if let Some(budget) = budget {
+                if started.elapsed() >= budget {
+                    break;
+                }
+            }
+
             if self.try_recv_queue().is_err() {
                 break;
             }
-            counter += 1;
+            executed += 1;
         }
-        counter
+
+        Some(ExecutionStats {
+            executed,
+            elapsed: started.elapsed(),
+        })
     }
 
     pub fn execute_query_with_timeout(&mut self, timeout: Duration, one_step_timeout: Duration) {
@@ -344,13 +834,54 @@ impl Queue {
         }
     }
 
+    /// Requests, which callbacks weren't executed yet.
+    /// Includes waiting and in flight requests, as well as ready responses.
     pub fn number_of_pending_requests(&self) -> usize {
         self.number_of_pending_requests
     }
+
+    /// Requests waiting for the concurrency limits.
+    pub fn number_of_waiting_requests(&self) -> usize {
+        self.concurrency_limits.lock().unwrap().waiting()
+    }
+
+    pub fn number_of_in_flight_requests(&self) -> usize {
+        self.concurrency_limits.lock().unwrap().in_flight()
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    /// Spawns HTTP server on the random local port. Each connection is answered with the next raw
+    /// response from the list, the last one is repeated forever.
+    fn spawn_local_server(responses: Vec<&'static str>) -> reqwest::Url {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        std::thread::spawn(move || {
+            for (i, stream) in listener.incoming().enumerate() {
+                let mut stream = stream.unwrap();
+
+                let mut request = Vec::new();
+                let mut buffer = [0; 1024];
+                while !request.ends_with(b"\r\n\r\n") {
+                    match stream.read(&mut buffer) {
+                        Ok(0) | Err(_) => break,
+                        Ok(n) => request.extend_from_slice(&buffer[..n]),
+                    }
+                }
+
+                let response = responses[std::cmp::min(i, responses.len() - 1)];
+                stream.write_all(response.as_bytes()).ok();
+            }
+        });
+
+        format!("http://127.0.0.1:{}/", port).parse().unwrap()
+    }
+
     #[test]
     fn test_basic_request() {
         use super::*;
@@ -368,7 +899,7 @@ mod tests {
                 .uri("https://docs.rs/".parse().unwrap())
                 .build()
                 .unwrap(),
-            move |req| {
+            move |req, _| {
                 *control_variable_c.lock().unwrap() = true;
                 assert!(String::from_utf8_lossy(&req.unwrap().body[..]).contains("docs.rs"));
             },
@@ -398,7 +929,7 @@ mod tests {
                 .uri("https://docs.rs/".parse().unwrap())
                 .build()
                 .unwrap(),
-            move |req| {
+            move |req, _| {
                 *control_variable_c.lock().unwrap() = true;
 
                 match req {
@@ -445,7 +976,7 @@ mod tests {
                 .uri("https://docs.rs/".parse().unwrap())
                 .build()
                 .unwrap(),
-            move |req| {
+            move |req, _| {
                 *control_variable_c.lock().unwrap() = true;
 
                 match req {
@@ -466,4 +997,476 @@ mod tests {
 
         assert_eq!(*control_variable.lock().unwrap(), true);
     }
+
+    #[test]
+    fn test_request_type_to_method() {
+        use super::*;
+
+        assert_eq!(
+            RequestType::Patch.to_method().unwrap(),
+            reqwest::Method::PATCH
+        );
+        assert_eq!(
+            RequestType::Head.to_method().unwrap(),
+            reqwest::Method::HEAD
+        );
+        assert_eq!(
+            RequestType::Options.to_method().unwrap(),
+            reqwest::Method::OPTIONS
+        );
+        assert_eq!(
+            RequestType::Custom("PROPFIND".to_owned())
+                .to_method()
+                .unwrap()
+                .as_str(),
+            "PROPFIND"
+        );
+        assert!(RequestType::Custom("BAD VERB".to_owned())
+            .to_method()
+            .is_err());
+        assert!(RequestType::Custom(String::new()).to_method().is_err());
+    }
+
+    #[test]
+    fn test_response_headers() {
+        use super::*;
+        use std::sync::{Arc, Mutex};
+
+        let mut queue = Queue::new();
+
+        let uri = spawn_local_server(vec![
+            "HTTP/1.1 200 OK\r\n\
+             ETag: \"abc\"\r\n\
+             Set-Cookie: a=1\r\n\
+             Set-Cookie: b=2\r\n\
+             Content-Length: 2\r\n\
+             Connection: close\r\n\r\nok",
+        ]);
+
+        let control_variable = Arc::new(Mutex::new(false));
+        let control_variable_c = Arc::clone(&control_variable);
+        let _handle = queue.send_request(
+            RequestBuilder::default()
+                .http_type(RequestType::Get)
+                .uri(uri)
+                .build()
+                .unwrap(),
+            move |req, _| {
+                let response = req.unwrap();
+                assert_eq!(&response.body[..], b"ok");
+                assert_eq!(response.headers.get("etag").unwrap(), "\"abc\"");
+
+                let cookies: Vec<_> = response.headers.get_all("SET-COOKIE").iter().collect();
+                assert_eq!(cookies, vec!["a=1", "b=2"]);
+
+                *control_variable_c.lock().unwrap() = true;
+            },
+        );
+
+        queue.execute_query_with_timeout(Duration::from_secs(2), Duration::from_millis(100));
+
+        assert!(*control_variable.lock().unwrap());
+    }
+
+    #[test]
+    fn test_execution_limits() {
+        use super::*;
+
+        let mut queue = Queue::new();
+
+        for _ in 0..4 {
+            // Cancelled right away, so responses are ready without network.
+            drop(
+                queue.send_request(
+                    RequestBuilder::default()
+                        .http_type(RequestType::Get)
+                        .uri("http://127.0.0.1:1/".parse().unwrap())
+                        .build()
+                        .unwrap(),
+                    |_, _| thread::sleep(Duration::from_millis(20)),
+                ),
+            );
+        }
+        thread::sleep(Duration::from_millis(500));
+
+        let stats = queue
+            .execute_queue_with_limit(10, Some(Duration::from_millis(1)), Duration::new(0, 0))
+            .unwrap();
+        assert_eq!(stats.executed, 1);
+        assert!(stats.elapsed >= Duration::from_millis(20));
+
+        assert!(queue
+            .execute_queue_with_limit(10, None, Duration::from_secs(60))
+            .is_none());
+        queue.last_time_executed_with_limit = None;
+
+        let stats = queue
+            .execute_queue_with_limit(2, None, Duration::new(0, 0))
+            .unwrap();
+        assert_eq!(stats.executed, 2);
+        assert_eq!(queue.number_of_pending_requests(), 1);
+    }
+
+    #[test]
+    fn test_weighted_dispatch() {
+        use super::*;
+        use std::sync::{Arc, Mutex};
+
+        let mut queue = Queue::new();
+        queue.set_group_weight(2, 2);
+
+        let executed = Arc::new(Mutex::new(vec![]));
+        for &group in &[1, 1, 1, 1, 2, 2, 2] {
+            let executed = Arc::clone(&executed);
+            drop(
+                queue.send_request(
+                    RequestBuilder::default()
+                        .http_type(RequestType::Get)
+                        .uri("http://127.0.0.1:1/".parse().unwrap())
+                        .group(group)
+                        .build()
+                        .unwrap(),
+                    move |_, _| executed.lock().unwrap().push(group),
+                ),
+            );
+        }
+        thread::sleep(Duration::from_millis(500));
+
+        let stats = queue
+            .execute_queue_with_limit(6, None, Duration::new(0, 0))
+            .unwrap();
+        assert_eq!(stats.executed, 6);
+
+        // Group 2 is sent later, but its weight is higher, so it's done before group 1.
+        let executed = executed.lock().unwrap();
+        assert_eq!(executed.iter().filter(|&&group| group == 2).count(), 3);
+        assert_ne!(&executed[..2], &[1, 1]);
+    }
+
+    #[test]
+    fn test_priority_dispatch() {
+        use super::*;
+        use std::sync::{Arc, Mutex};
+
+        let mut queue = Queue::new();
+
+        let executed = Arc::new(Mutex::new(vec![]));
+        for &priority in &[
+            RequestPriority::Low,
+            RequestPriority::Normal,
+            RequestPriority::High,
+            RequestPriority::Low,
+            RequestPriority::High,
+        ] {
+            let executed = Arc::clone(&executed);
+            drop(
+                queue.send_request(
+                    RequestBuilder::default()
+                        .http_type(RequestType::Get)
+                        .uri("http://127.0.0.1:1/".parse().unwrap())
+                        .options(
+                            RequestOptionsBuilder::default()
+                                .priority(priority)
+                                .build()
+                                .unwrap(),
+                        )
+                        .build()
+                        .unwrap(),
+                    move |_, _| executed.lock().unwrap().push(priority),
+                ),
+            );
+        }
+        thread::sleep(Duration::from_millis(500));
+
+        queue.execute_queue_with_limit(5, None, Duration::new(0, 0));
+
+        use RequestPriority::*;
+        assert_eq!(
+            *executed.lock().unwrap(),
+            vec![High, High, Normal, Low, Low]
+        );
+    }
+
+    #[test]
+    fn test_retry_delay() {
+        use super::*;
+
+        let mut policy = RetryPolicy {
+            max_attempts: 10,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(1),
+            jitter: 0.0,
+            conditions: RetryConditions::default(),
+        };
+
+        let delays: Vec<_> = (1..=6).map(|attempt| policy.delay(attempt)).collect();
+        assert_eq!(
+            delays,
+            [100, 200, 400, 800, 1000, 1000]
+                .iter()
+                .map(|&ms| Duration::from_millis(ms))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(policy.delay(1000), Duration::from_secs(1));
+
+        policy.jitter = 1.0;
+        assert!(policy.delay(3) <= Duration::from_millis(400));
+
+        assert!(policy.should_retry_status(reqwest::StatusCode::SERVICE_UNAVAILABLE));
+        assert!(policy.should_retry_status(reqwest::StatusCode::TOO_MANY_REQUESTS));
+        assert!(!policy.should_retry_status(reqwest::StatusCode::NOT_FOUND));
+    }
+
+    #[test]
+    fn test_retries() {
+        use super::*;
+        use std::sync::{Arc, Mutex};
+
+        let mut queue = Queue::new();
+
+        let uri = spawn_local_server(vec![
+            "HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            "HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok",
+        ]);
+
+        let retry = RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(10),
+            jitter: 0.0,
+            ..RetryPolicy::default()
+        };
+
+        let results = Arc::new(Mutex::new(vec![]));
+        let mut handles = vec![];
+        for uri in [uri, "http://127.0.0.1:1/".parse().unwrap()].iter() {
+            let results = Arc::clone(&results);
+            handles.push(
+                queue.send_request(
+                    RequestBuilder::default()
+                        .http_type(RequestType::Get)
+                        .uri(uri.clone())
+                        .options(
+                            RequestOptionsBuilder::default()
+                                .retry(retry.clone())
+                                .build()
+                                .unwrap(),
+                        )
+                        .build()
+                        .unwrap(),
+                    move |response, info| {
+                        let status = response.ok().map(|response| response.status_code);
+                        results.lock().unwrap().push((status, info.attempts));
+                    },
+                ),
+            );
+        }
+
+        queue.execute_query_with_timeout(Duration::from_secs(2), Duration::from_millis(100));
+
+        let mut results = results.lock().unwrap().clone();
+        results.sort_by_key(|(status, _)| status.is_none());
+        assert_eq!(results, vec![(Some(reqwest::StatusCode::OK), 2), (None, 3)]);
+    }
+
+    #[test]
+    fn test_retry_after() {
+        use super::*;
+        use std::sync::{Arc, Mutex};
+
+        let mut queue = Queue::new();
+
+        let uri = spawn_local_server(vec![
+            "HTTP/1.1 429 Too Many Requests\r\nRetry-After: 1\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            "HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok",
+        ]);
+
+        let info = Arc::new(Mutex::new(None));
+        let info_c = Arc::clone(&info);
+        let _handle = queue.send_request(
+            RequestBuilder::default()
+                .http_type(RequestType::Get)
+                .uri(uri)
+                .options(
+                    RequestOptionsBuilder::default()
+                        .retry(RetryPolicy {
+                            max_attempts: 2,
+                            base_delay: Duration::from_millis(10),
+                            ..RetryPolicy::default()
+                        })
+                        .build()
+                        .unwrap(),
+                )
+                .build()
+                .unwrap(),
+            move |response, info| {
+                assert_eq!(response.unwrap().status_code, reqwest::StatusCode::OK);
+                *info_c.lock().unwrap() = Some(info);
+            },
+        );
+
+        queue.execute_query_with_timeout(Duration::from_secs(3), Duration::from_millis(100));
+
+        let info = info.lock().unwrap().unwrap();
+        assert_eq!(info.attempts, 2);
+        assert!(info.held >= Duration::from_millis(900));
+    }
+
+    #[test]
+    fn test_cancelling_waiting_requests() {
+        use super::*;
+        use std::net::TcpListener;
+        use std::sync::{Arc, Mutex};
+
+        let mut queue = Queue::new();
+        queue.set_concurrency_limits(1, 0);
+
+        // Connections are never accepted, so the first request stays in flight.
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let uri: reqwest::Url = format!("http://{}/", listener.local_addr().unwrap())
+            .parse()
+            .unwrap();
+
+        let results = Arc::new(Mutex::new(vec![]));
+        let mut handles = vec![];
+        for i in 0..3 {
+            let results = Arc::clone(&results);
+            handles.push(
+                queue.send_request(
+                    RequestBuilder::default()
+                        .http_type(RequestType::Get)
+                        .uri(uri.clone())
+                        .options(
+                            RequestOptionsBuilder::default()
+                                .timeout(Some(Duration::from_secs(2)))
+                                .build()
+                                .unwrap(),
+                        )
+                        .build()
+                        .unwrap(),
+                    move |response, info| {
+                        let cancelled =
+                            matches!(response.err().unwrap().kind(), ErrorKind::RequestCancelled);
+                        results.lock().unwrap().push((i, cancelled, info.attempts));
+                    },
+                ),
+            );
+        }
+
+        thread::sleep(Duration::from_millis(200));
+        assert_eq!(queue.number_of_waiting_requests(), 2);
+
+        // Cancelled request leaves the queue at once, without being sent.
+        drop(handles.pop());
+        queue.execute_query_with_timeout(Duration::from_millis(300), Duration::from_millis(50));
+        assert_eq!(queue.number_of_waiting_requests(), 1);
+        assert_eq!(queue.number_of_in_flight_requests(), 1);
+        assert_eq!(*results.lock().unwrap(), vec![(2, true, 0)]);
+    }
+
+    #[test]
+    fn test_rate_limit_groups() {
+        use super::*;
+        use std::sync::{Arc, Mutex};
+
+        let mut queue = Queue::new();
+        let mut groups = HashMap::new();
+        groups.insert("api".to_owned(), "1, 1".parse().unwrap());
+        queue.set_rate_limit_groups(&groups);
+
+        let results = Arc::new(Mutex::new(vec![]));
+        let mut handles = vec![];
+        for &timeout in &[100, 100, 2000] {
+            let results = Arc::clone(&results);
+            handles.push(
+                queue.send_request(
+                    RequestBuilder::default()
+                        .http_type(RequestType::Get)
+                        .uri("http://127.0.0.1:1/".parse().unwrap())
+                        .options(
+                            RequestOptionsBuilder::default()
+                                .timeout(Some(Duration::from_millis(timeout)))
+                                .rate_limit_group(Some("api".to_owned()))
+                                .build()
+                                .unwrap(),
+                        )
+                        .build()
+                        .unwrap(),
+                    move |response, info| {
+                        let rate_limited = matches!(
+                            response.err().unwrap().kind(),
+                            ErrorKind::RequestRateLimited
+                        );
+                        results.lock().unwrap().push((rate_limited, info.attempts));
+                    },
+                ),
+            );
+            thread::sleep(Duration::from_millis(50));
+        }
+
+        queue.execute_query_with_timeout(Duration::from_secs(2), Duration::from_millis(100));
+
+        // Second request can't wait for the token, third one waits for it.
+        assert_eq!(
+            *results.lock().unwrap(),
+            vec![(false, 1), (true, 0), (false, 1)]
+        );
+    }
+
+    #[test]
+    fn test_concurrency_limits() {
+        use super::*;
+        use std::net::TcpListener;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        let mut queue = Queue::new();
+        queue.set_concurrency_limits(1, 0);
+
+        // Connections are never accepted, so requests stay in flight until timeout.
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let uri: reqwest::Url = format!("http://{}/", listener.local_addr().unwrap())
+            .parse()
+            .unwrap();
+
+        let timeouts = Arc::new(AtomicUsize::new(0));
+        let mut handles = vec![];
+        for _ in 0..3 {
+            let timeouts = Arc::clone(&timeouts);
+            handles.push(
+                queue.send_request(
+                    RequestBuilder::default()
+                        .http_type(RequestType::Get)
+                        .uri(uri.clone())
+                        .options(
+                            RequestOptionsBuilder::default()
+                                .timeout(Some(Duration::from_millis(400)))
+                                .build()
+                                .unwrap(),
+                        )
+                        .build()
+                        .unwrap(),
+                    move |response, _| {
+                        if let ErrorKind::RequestTimeout = response.err().unwrap().kind() {
+                            timeouts.fetch_add(1, Ordering::SeqCst);
+                        }
+                    },
+                ),
+            );
+        }
+
+        thread::sleep(Duration::from_millis(200));
+        assert_eq!(queue.number_of_in_flight_requests(), 1);
+        assert_eq!(queue.number_of_waiting_requests(), 2);
+        assert_eq!(queue.number_of_pending_requests(), 3);
+
+        // Raising the limit starts waiting requests.
+        queue.set_concurrency_limits(0, 0);
+        assert_eq!(queue.number_of_in_flight_requests(), 3);
+        assert_eq!(queue.number_of_waiting_requests(), 0);
+
+        queue.execute_query_with_timeout(Duration::from_secs(1), Duration::from_millis(100));
+        assert_eq!(timeouts.load(Ordering::SeqCst), 3);
+        assert_eq!(queue.number_of_in_flight_requests(), 0);
+        assert_eq!(queue.number_of_pending_requests(), 0);
+    }
 }
@@ -0,0 +1,362 @@
+/*
+ * gRIP
+ * Copyright (c) 2018 Alik Aslanyan <cplusplus256@gmail.com>
+ *
+ *
+ *    This program is free software; you can redistribute it and/or modify it
+ *    under the terms of the GNU General Public License as published by the
+ *    Free Software Foundation; either version 3 of the License, or (at
+ *    your option) any later version.
+ *
+ *    This program is distributed in the hope that it will be useful, but
+ *    WITHOUT ANY WARRANTY; without even the implied warranty of
+ *    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+ *    General Public License for more details.
+ *
+ *    You should have received a copy of the GNU General Public License
+ *    along with this program; if not, write to the Free Software Foundation,
+ *    Inc., 59 Temple Place, Suite 330, Boston, MA  02111-1307  USA
+ *
+ *    In addition, as a special exception, the author gives permission to
+ *    link the code of this program with the Half-Life Game Engine ("HL
+ *    Engine") and Modified Game Libraries ("MODs") developed by Valve,
+ *    L.L.C ("Valve").  You must obey the GNU General Public License in all
+ *    respects for all of the code used other than the HL Engine and MODs
+ *    from Valve.  If you modify this file, you may extend this exception
+ *    to your version of the file, but you are not obligated to do so.  If
+ *    you do not wish to do so, delete this exception statement from your
+ *    version.
+ *
+ */
+
+use reqwest::header::HeaderMap;
+use reqwest::StatusCode;
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+/// Values of `X-RateLimit-Reset` greater than this are unix timestamps, otherwise delays.
+const UNIX_TIMESTAMP_THRESHOLD: u64 = 1_000_000_000;
+
+/// Tracks hosts, which asked to stop sending requests for some time,
+/// using `Retry-After` or `X-RateLimit-Remaining`/`X-RateLimit-Reset` headers.
+#[derive(Debug)]
+pub struct RateLimits {
+    blocked_until: HashMap<String, Instant>,
+    /// Requests are never held longer than that, whatever host asks.
+    pub max_hold: Duration,
+}
+
+impl Default for RateLimits {
+    fn default() -> Self {
+        RateLimits {
+            blocked_until: HashMap::new(),
+            max_hold: Duration::from_secs(60),
+        }
+    }
+}
+
+impl RateLimits {
+    /// Time left until requests to the host can be sent.
+    pub fn hold_time(&mut self, host: &str, now: Instant) -> Duration {
+        match self.blocked_until.get(host) {
+            Some(&until) if until > now => until - now,
+            Some(_) => {
+                self.blocked_until.remove(host);
+                Duration::new(0, 0)
+            }
+            None => Duration::new(0, 0),
+        }
+    }
+
+    /// Blocks host if response asks to. `now_unix` is used for HTTP dates and timestamps.
+    pub fn update(
+        &mut self,
+        host: &str,
+        status_code: StatusCode,
+        headers: &HeaderMap,
+        now: Instant,
+        now_unix: i64,
+    ) {
+        let header = |name| headers.get(name).and_then(|value| value.to_str().ok());
+
+        let retry_after = match status_code {
+            StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE => {
+                header("retry-after").and_then(|value| parse_retry_after(value, now_unix))
+            }
+            _ => None,
+        };
+
+        let reset = match header("x-ratelimit-remaining").map(str::trim) {
+            Some("0") => header("x-ratelimit-reset")
+                .and_then(|value| parse_rate_limit_reset(value, now_unix)),
+            _ => None,
+        };
+
+        if let Some(delay) = retry_after.into_iter().chain(reset).max() {
+            let until = now + std::cmp::min(delay, self.max_hold);
+            let blocked_until = self.blocked_until.entry(host.to_owned()).or_insert(until);
+            *blocked_until = std::cmp::max(*blocked_until, until);
+        }
+    }
+}
+
+/// Token bucket of the named group: `rate` tokens per second are added, up to `burst` tokens.
+/// Written as `rate, burst` in the config.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RateLimitGroup {
+    pub rate: f64,
+    pub burst: usize,
+}
+
+impl FromStr for RateLimitGroup {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let mut parts = value.split(',').map(str::trim);
+        let (rate, burst) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(rate), Some(burst), None) => (rate, burst),
+            _ => return Err("expected `rate, burst`".to_owned()),
+        };
+
+        let rate: f64 = rate.parse().map_err(|e| format!("rate: {}", e))?;
+        if !rate.is_finite() || rate <= 0.0 {
+            return Err("rate should be greater than zero".to_owned());
+        }
+
+        let burst: usize = burst.parse().map_err(|e| format!("burst: {}", e))?;
+        if burst == 0 {
+            return Err("burst should be greater than zero".to_owned());
+        }
+
+        Ok(RateLimitGroup { rate, burst })
+    }
+}
+
+impl Display for RateLimitGroup {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}, {}", self.rate, self.burst)
+    }
+}
+
+#[derive(Debug)]
+struct TokenBucket {
+    group: RateLimitGroup,
+    /// Negative when tokens are taken in advance by the waiting requests.
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    fn refill(&mut self, now: Instant) {
+        if now > self.updated {
+            let elapsed = (now - self.updated).as_secs_f64();
+            self.tokens = (self.tokens + elapsed * self.group.rate).min(self.group.burst as f64);
+            self.updated = now;
+        }
+    }
+}
+
+/// Token buckets of the rate limit groups, which are configured in grip.ini.
+#[derive(Debug, Default)]
+pub struct TokenBuckets {
+    buckets: HashMap<String, TokenBucket>,
+}
+
+impl TokenBuckets {
+    /// Replaces groups. Kept groups keep their tokens, new groups start with full bucket.
+    pub fn set_groups(&mut self, groups: &HashMap<String, RateLimitGroup>, now: Instant) {
+        self.buckets.retain(|name, _| groups.contains_key(name));
+        for (name, &group) in groups {
+            let bucket = self
+                .buckets
+                .entry(name.clone())
+                .or_insert_with(|| TokenBucket {
+                    group,
+                    tokens: group.burst as f64,
+                    updated: now,
+                });
+
+            bucket.refill(now);
+            bucket.group = group;
+            bucket.tokens = bucket.tokens.min(group.burst as f64);
+        }
+    }
+
+    /// Takes token of the group and returns how long to wait until it's available.
+    /// Returns `None` and doesn't take the token, if wait would be longer than `max_wait`.
+    /// Unknown groups aren't limited.
+    pub fn acquire(&mut self, group: &str, now: Instant, max_wait: Duration) -> Option<Duration> {
+        let bucket = match self.buckets.get_mut(group) {
+            Some(bucket) => bucket,
+            None => return Some(Duration::new(0, 0)),
+        };
+
+        bucket.refill(now);
+        let wait = if bucket.tokens >= 1.0 {
+            Duration::new(0, 0)
+        } else {
+            Duration::from_secs_f64((1.0 - bucket.tokens) / bucket.group.rate)
+        };
+
+        if wait > max_wait {
+            None
+        } else {
+            bucket.tokens -= 1.0;
+            Some(wait)
+        }
+    }
+
+    /// Gives back token taken by `acquire`, when request wasn't sent.
+    pub fn release(&mut self, group: &str) {
+        if let Some(bucket) = self.buckets.get_mut(group) {
+            bucket.tokens = (bucket.tokens + 1.0).min(bucket.group.burst as f64);
+        }
+    }
+}
+
+/// Key of the host, which includes port.
+pub fn host_key(uri: &reqwest::Url) -> String {
+    format!(
+        "{}:{}",
+        uri.host_str().unwrap_or_default(),
+        uri.port_or_known_default().unwrap_or_default()
+    )
+}
+
+/// `Retry-After` is either delay in seconds or HTTP date.
+fn parse_retry_after(value: &str, now_unix: i64) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let date = time::strptime(value, "%a, %d %b %Y %H:%M:%S GMT").ok()?;
+    Some(Duration::from_secs(
+        std::cmp::max(date.to_timespec().sec - now_unix, 0) as u64,
+    ))
+}
+
+/// `X-RateLimit-Reset` is either delay in seconds or unix timestamp, depending on the API.
+fn parse_rate_limit_reset(value: &str, now_unix: i64) -> Option<Duration> {
+    let value: u64 = value.trim().parse().ok()?;
+    Some(Duration::from_secs(if value > UNIX_TIMESTAMP_THRESHOLD {
+        std::cmp::max(value as i64 - now_unix, 0) as u64
+    } else {
+        value
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    #[test]
+    fn header_parsing() {
+        let now = 1_500_000_000;
+        assert_eq!(
+            parse_retry_after("120", now),
+            Some(Duration::from_secs(120))
+        );
+        // Fri, 14 Jul 2017 02:40:00 GMT is 1_500_000_000.
+        assert_eq!(
+            parse_retry_after("Fri, 14 Jul 2017 02:40:30 GMT", now),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            parse_retry_after("Fri, 14 Jul 2017 02:39:00 GMT", now),
+            Some(Duration::from_secs(0))
+        );
+        assert_eq!(parse_retry_after("soon", now), None);
+
+        assert_eq!(
+            parse_rate_limit_reset("15", now),
+            Some(Duration::from_secs(15))
+        );
+        assert_eq!(
+            parse_rate_limit_reset("1500000045", now),
+            Some(Duration::from_secs(45))
+        );
+    }
+
+    #[test]
+    fn token_buckets() {
+        assert_eq!(
+            " 2.5, 3".parse(),
+            Ok(RateLimitGroup {
+                rate: 2.5,
+                burst: 3
+            })
+        );
+        assert!("0, 3".parse::<RateLimitGroup>().is_err());
+        assert!("1, 0".parse::<RateLimitGroup>().is_err());
+        assert!("1".parse::<RateLimitGroup>().is_err());
+
+        let now = Instant::now();
+        let mut groups = HashMap::new();
+        groups.insert("a".to_owned(), "2, 2".parse().unwrap());
+
+        let mut buckets = TokenBuckets::default();
+        buckets.set_groups(&groups, now);
+
+        let max_wait = Duration::from_secs(1);
+        let zero = Duration::new(0, 0);
+        assert_eq!(buckets.acquire("a", now, max_wait), Some(zero));
+        assert_eq!(buckets.acquire("a", now, max_wait), Some(zero));
+        assert_eq!(
+            buckets.acquire("a", now, max_wait),
+            Some(Duration::from_millis(500))
+        );
+        assert_eq!(
+            buckets.acquire("a", now, max_wait),
+            Some(Duration::from_secs(1))
+        );
+        assert_eq!(buckets.acquire("a", now, max_wait), None);
+        assert_eq!(buckets.acquire("b", now, max_wait), Some(zero));
+
+        // Released token is available for the next request.
+        buckets.release("a");
+        assert_eq!(
+            buckets.acquire("a", now, max_wait),
+            Some(Duration::from_secs(1))
+        );
+
+        // Tokens are refilled over time and kept, when groups are reloaded.
+        let now = now + Duration::from_secs(2);
+        groups.insert("a".to_owned(), "1, 1".parse().unwrap());
+        buckets.set_groups(&groups, now);
+        assert_eq!(buckets.acquire("a", now, max_wait), Some(zero));
+        assert_eq!(
+            buckets.acquire("a", now, max_wait),
+            Some(Duration::from_secs(1))
+        );
+    }
+
+    #[test]
+    fn hosts_are_held() {
+        let mut limits = RateLimits::default();
+        let now = Instant::now();
+
+        let mut headers = HeaderMap::new();
+        headers.insert("retry-after", HeaderValue::from_static("10"));
+        limits.update("a:80", StatusCode::OK, &headers, now, 0);
+        assert_eq!(limits.hold_time("a:80", now), Duration::new(0, 0));
+
+        limits.update("a:80", StatusCode::TOO_MANY_REQUESTS, &headers, now, 0);
+        assert_eq!(limits.hold_time("a:80", now), Duration::from_secs(10));
+        assert_eq!(limits.hold_time("b:80", now), Duration::new(0, 0));
+
+        let mut headers = HeaderMap::new();
+        headers.insert("x-ratelimit-remaining", HeaderValue::from_static("0"));
+        headers.insert("x-ratelimit-reset", HeaderValue::from_static("3600"));
+        limits.update("b:80", StatusCode::OK, &headers, now, 0);
+        assert_eq!(limits.hold_time("b:80", now), limits.max_hold);
+
+        assert_eq!(
+            limits.hold_time("a:80", now + Duration::from_secs(11)),
+            Duration::new(0, 0)
+        );
+    }
+}